@@ -1,11 +1,13 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use smallobjectpool::{
-    arraylike::ArrayLike, linkedlist::LinkedList, ptrbased::PtrBased,
-    smallobjectpool::SmallObjectPool,
+    arraylike::ArrayLike, inlinepool::InlinePool, linkedlist::LinkedList, poolvec::PoolVec,
+    ptrbased::PtrBased, slotmap::PoolSlotMap, smallobjectpool::SmallObjectPool,
 };
 
 const BLOCK_SIZE: usize = 1024;
 const ITERS: i32 = 1024 * 10;
+const SHORT_LIVED: usize = 64;
+#[allow(clippy::upper_case_acronyms)]
 type DTYPE = f64;
 
 pub fn array_like_direct_insert_benchmark(c: &mut Criterion) {
@@ -60,6 +62,69 @@ pub fn sop_push_benchmark(c: &mut Criterion) {
     });
 }
 
+pub fn pool_vec_push_pop_benchmark(c: &mut Criterion) {
+    c.bench_function("pool vec push pop", |b| {
+        b.iter(|| {
+            let mut vec: PoolVec<DTYPE, BLOCK_SIZE> = PoolVec::new();
+            for i in 0..ITERS {
+                vec.push(i as DTYPE);
+            }
+            while let Some(value) = vec.pop() {
+                black_box(value);
+            }
+            black_box(vec);
+        });
+    });
+}
+
+pub fn vec_push_pop_benchmark(c: &mut Criterion) {
+    c.bench_function("vec push pop", |b| {
+        b.iter(|| {
+            let mut vec: Vec<DTYPE> = Vec::new();
+            for i in 0..ITERS {
+                vec.push(i as DTYPE);
+            }
+            while let Some(value) = vec.pop() {
+                black_box(value);
+            }
+            black_box(vec);
+        });
+    });
+}
+
+pub fn sop_unused_benchmark(c: &mut Criterion) {
+    c.bench_function("sop unused", |b| {
+        b.iter(|| {
+            let list: SmallObjectPool<DTYPE, BLOCK_SIZE> = SmallObjectPool::new();
+            black_box(list);
+        });
+    });
+}
+
+pub fn sop_short_lived_push_benchmark(c: &mut Criterion) {
+    c.bench_function("sop short-lived push", |b| {
+        b.iter(|| {
+            let mut list: SmallObjectPool<DTYPE, SHORT_LIVED> = SmallObjectPool::new();
+            for i in 0..SHORT_LIVED {
+                list.push(i as DTYPE);
+            }
+            black_box(list);
+        });
+    });
+}
+
+pub fn inline_pool_short_lived_push_benchmark(c: &mut Criterion) {
+    c.bench_function("inline pool short-lived push", |b| {
+        b.iter(|| {
+            let mut list: InlinePool<DTYPE, SHORT_LIVED> = InlinePool::new();
+            for i in 0..SHORT_LIVED {
+                list.push(i as DTYPE);
+            }
+            black_box(list);
+        });
+    });
+}
+
 pub fn vec_high_vol_push_benchmark(c: &mut Criterion) {
     c.bench_function("vec high volume push", |b| {
         b.iter(|| {
@@ -72,12 +137,60 @@ pub fn vec_high_vol_push_benchmark(c: &mut Criterion) {
     });
 }
 
+pub fn vec_iter_benchmark(c: &mut Criterion) {
+    let vec: Vec<DTYPE> = (0..ITERS).map(|i| i as DTYPE).collect();
+    c.bench_function("vec iter", |b| {
+        b.iter(|| black_box(vec.iter().sum::<DTYPE>()));
+    });
+}
+
+pub fn sop_iter_benchmark(c: &mut Criterion) {
+    let mut list: SmallObjectPool<DTYPE, BLOCK_SIZE> = SmallObjectPool::new();
+    for i in 0..ITERS {
+        list.push(i as DTYPE);
+    }
+    c.bench_function("sop iter", |b| {
+        b.iter(|| black_box(list.iter().sum::<DTYPE>()));
+    });
+}
+
+pub fn slotmap_iter_benchmark(c: &mut Criterion) {
+    let mut map = slotmap::SlotMap::new();
+    let keys: Vec<_> = (0..ITERS).map(|i| map.insert(i as DTYPE)).collect();
+    keys.iter().step_by(3).for_each(|key| {
+        map.remove(*key);
+    });
+    c.bench_function("slotmap iter", |b| {
+        b.iter(|| black_box(map.values().sum::<DTYPE>()));
+    });
+}
+
+pub fn pool_slot_map_iter_benchmark(c: &mut Criterion) {
+    let mut map: PoolSlotMap<DTYPE, BLOCK_SIZE> = PoolSlotMap::new();
+    let keys: Vec<_> = (0..ITERS).map(|i| map.insert(i as DTYPE)).collect();
+    keys.iter().step_by(3).for_each(|key| {
+        map.remove(*key);
+    });
+    c.bench_function("pool slot map iter", |b| {
+        b.iter(|| black_box(map.values().sum::<DTYPE>()));
+    });
+}
+
 criterion_group!(
     benches,
     vec_push_benchmark,
     array_like_direct_insert_benchmark,
     sop_push_benchmark,
+    sop_unused_benchmark,
+    sop_short_lived_push_benchmark,
+    inline_pool_short_lived_push_benchmark,
+    vec_push_pop_benchmark,
+    pool_vec_push_pop_benchmark,
     linked_list_push_benchmark,
-    vec_high_vol_push_benchmark
+    vec_high_vol_push_benchmark,
+    vec_iter_benchmark,
+    sop_iter_benchmark,
+    slotmap_iter_benchmark,
+    pool_slot_map_iter_benchmark
 );
 criterion_main!(benches);
@@ -0,0 +1,156 @@
+//! An arithmetic expression interpreter whose syntax tree lives in a
+//! `SmallObjectPool`. Nodes refer to their children through `PoolHandle`s,
+//! and the whole tree is dropped at once by rewinding the pool before the
+//! next expression is parsed.
+//!
+//! ```text
+//! cargo run --example expr -- "(1 + 2) * x - 4 / 8" 3
+//! ```
+
+use smallobjectpool::{handle::PoolHandle, smallobjectpool::SmallObjectPool};
+
+#[derive(Clone, Copy, Debug)]
+enum Expr {
+    Num(f64),
+    Var,
+    Neg(PoolHandle<Expr>),
+    Bin(Op, PoolHandle<Expr>, PoolHandle<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+type Ast = SmallObjectPool<Expr, 64>;
+
+/// Recursive descent parser pushing every node it builds to the pool.
+struct Parser<'a> {
+    ast: &'a mut Ast,
+    tokens: Vec<char>,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.tokens.get(self.at).is_some_and(|c| c.is_whitespace()) {
+            self.at += 1;
+        }
+        self.tokens.get(self.at).copied()
+    }
+
+    fn expect(&mut self, c: char) {
+        assert_eq!(self.peek(), Some(c), "expected {c:?} at {}", self.at);
+        self.at += 1;
+    }
+
+    fn expression(&mut self) -> PoolHandle<Expr> {
+        let mut lhs = self.term();
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.at += 1;
+            let rhs = self.term();
+            let op = if op == '+' { Op::Add } else { Op::Sub };
+            lhs = self.ast.push_handle(Expr::Bin(op, lhs, rhs));
+        }
+        lhs
+    }
+
+    fn term(&mut self) -> PoolHandle<Expr> {
+        let mut lhs = self.factor();
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.at += 1;
+            let rhs = self.factor();
+            let op = if op == '*' { Op::Mul } else { Op::Div };
+            lhs = self.ast.push_handle(Expr::Bin(op, lhs, rhs));
+        }
+        lhs
+    }
+
+    fn factor(&mut self) -> PoolHandle<Expr> {
+        match self.peek() {
+            Some('(') => {
+                self.at += 1;
+                let inner = self.expression();
+                self.expect(')');
+                inner
+            }
+            Some('-') => {
+                self.at += 1;
+                let inner = self.factor();
+                self.ast.push_handle(Expr::Neg(inner))
+            }
+            Some('x') => {
+                self.at += 1;
+                self.ast.push_handle(Expr::Var)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.at;
+                while self
+                    .tokens
+                    .get(self.at)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.at += 1;
+                }
+                let literal: String = self.tokens[start..self.at].iter().collect();
+                let value = literal.parse().expect("number literal");
+                self.ast.push_handle(Expr::Num(value))
+            }
+            other => panic!("unexpected {other:?} at {}", self.at),
+        }
+    }
+}
+
+fn parse(ast: &mut Ast, source: &str) -> PoolHandle<Expr> {
+    let mut parser = Parser {
+        ast,
+        tokens: source.chars().collect(),
+        at: 0,
+    };
+    let root = parser.expression();
+    assert_eq!(parser.peek(), None, "trailing input at {}", parser.at);
+    root
+}
+
+fn eval(ast: &Ast, node: PoolHandle<Expr>, x: f64) -> f64 {
+    match *ast.resolve(&node).expect("node of the current tree") {
+        Expr::Num(value) => value,
+        Expr::Var => x,
+        Expr::Neg(inner) => -eval(ast, inner, x),
+        Expr::Bin(op, lhs, rhs) => {
+            let (lhs, rhs) = (eval(ast, lhs, x), eval(ast, rhs, x));
+            match op {
+                Op::Add => lhs + rhs,
+                Op::Sub => lhs - rhs,
+                Op::Mul => lhs * rhs,
+                Op::Div => lhs / rhs,
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let source = args.next().unwrap_or_else(|| "(1 + 2) * x - 4 / 8".into());
+    let x: f64 = args.next().map_or(3.0, |arg| arg.parse().expect("x"));
+
+    let mut ast = Ast::new();
+    let root = parse(&mut ast, &source);
+    println!(
+        "{source} = {} at x = {x} ({} nodes)",
+        eval(&ast, root, x),
+        ast.len()
+    );
+
+    // Parsing the next expression reuses the blocks of the previous tree,
+    // whose handles no longer resolve.
+    ast.rewind();
+    assert!(ast.resolve(&root).is_none());
+    let root = parse(&mut ast, "-(x * x) + 2 * x");
+    for x in [0.0, 1.0, 2.0] {
+        println!("-(x * x) + 2 * x = {} at x = {x}", eval(&ast, root, x));
+    }
+}
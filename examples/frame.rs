@@ -0,0 +1,101 @@
+//! A per-frame allocator for a game loop. Everything a frame needs only
+//! while it runs goes to frame pools that are rewound at the end of the
+//! frame, so after the first few frames the loop no longer touches the
+//! global allocator.
+//!
+//! ```text
+//! cargo run --example frame -- [frames]
+//! ```
+
+use std::alloc::Layout;
+
+use smallobjectpool::{rawpool::RawPool, smallobjectpool::SmallObjectPool};
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Contact {
+    particle: usize,
+    depth: f32,
+}
+
+/// Pools cleared at the end of every frame.
+struct FrameArena {
+    contacts: SmallObjectPool<Contact, 256>,
+    scratch: RawPool<4096>,
+}
+
+impl FrameArena {
+    fn new() -> Self {
+        FrameArena {
+            contacts: SmallObjectPool::new(),
+            scratch: RawPool::new(),
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.contacts.rewind();
+        self.scratch.rewind();
+    }
+}
+
+fn simulate(particles: &mut [Particle], frame: &mut FrameArena, dt: f32) -> f32 {
+    for (index, particle) in particles.iter_mut().enumerate() {
+        particle.velocity[1] -= 9.81 * dt;
+        particle.position[0] += particle.velocity[0] * dt;
+        particle.position[1] += particle.velocity[1] * dt;
+        if particle.position[1] < 0.0 {
+            frame.contacts.push(Contact {
+                particle: index,
+                depth: -particle.position[1],
+            });
+        }
+    }
+    // A temporary buffer of a size only known during the frame, in the
+    // untyped scratch pool.
+    let count = frame.contacts.len();
+    let depths = frame
+        .scratch
+        .alloc(Layout::array::<f32>(count.max(1)).unwrap())
+        .cast::<f32>();
+    for (i, contact) in frame.contacts.iter().enumerate() {
+        unsafe { depths.add(i).write(contact.depth) };
+        let particle = &mut particles[contact.particle];
+        particle.position[1] = 0.0;
+        particle.velocity[1] *= -0.5;
+    }
+    let depths = unsafe { std::slice::from_raw_parts(depths.as_ptr(), count) };
+    depths.iter().copied().fold(0.0, f32::max)
+}
+
+fn main() {
+    let frames: usize = std::env::args()
+        .nth(1)
+        .map_or(600, |arg| arg.parse().expect("frames"));
+    let mut particles: Vec<Particle> = (0..1000)
+        .map(|i| Particle {
+            position: [i as f32, 1.0 + (i % 7) as f32],
+            velocity: [((i % 3) as f32 - 1.0) * 0.1, 0.0],
+        })
+        .collect();
+    let mut frame = FrameArena::new();
+    let mut blocks = 0;
+    for index in 0..frames {
+        let deepest = simulate(&mut particles, &mut frame, 1.0 / 60.0);
+        if index % 120 == 0 {
+            println!(
+                "frame {index}: {} contacts, deepest {deepest:.3}, {} contact blocks",
+                frame.contacts.len(),
+                frame.contacts.block_count()
+            );
+        }
+        frame.contacts.validate().expect("contact pool invariants");
+        blocks = blocks.max(frame.contacts.block_count());
+        frame.end_frame();
+    }
+    println!("{frames} frames used at most {blocks} contact blocks");
+}
@@ -0,0 +1,119 @@
+//! Long-running randomized push/mark/rewind cycles against a `SmallObjectPool`,
+//! checking the pool against a `Vec` model and tracking resident memory.
+//!
+//! ```text
+//! cargo run --release --example soak --features soak -- [seconds] [seed]
+//! ```
+//!
+//! To soak your own element type, call `soak` with a function building a
+//! value from a random number.
+
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use smallobjectpool::smallobjectpool::SmallObjectPool;
+
+const CAP: usize = 256;
+
+/// xorshift64*, so the soak is reproducible from its seed without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Resident set size in bytes, where the platform exposes it.
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+fn check<T: Copy + PartialEq + Debug>(pool: &SmallObjectPool<T, CAP>, model: &[T]) {
+    pool.validate().expect("pool invariants");
+    assert!(
+        pool.iter().copied().eq(model.iter().copied()),
+        "pool contents diverged from the model"
+    );
+}
+
+pub fn soak<T: Copy + PartialEq + Debug>(duration: Duration, seed: u64, make: impl Fn(u64) -> T) {
+    let mut rng = Rng(seed.max(1));
+    let mut pool = SmallObjectPool::<T, CAP>::new();
+    let mut model = Vec::new();
+    let mut marked = 0;
+    let mut max_len: usize = 0;
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut cycles: u64 = 0;
+
+    while start.elapsed() < duration {
+        match rng.below(100) {
+            0..=59 => {
+                for _ in 0..rng.below(4 * CAP as u64) {
+                    let value = make(rng.next());
+                    pool.push(value);
+                    model.push(value);
+                }
+            }
+            60..=69 => {
+                let value = make(rng.next());
+                assert_eq!(*pool.alloc(value), value);
+                model.push(value);
+            }
+            70..=79 => {
+                pool.mark();
+                marked = model.len();
+            }
+            80..=94 => {
+                if marked <= model.len() {
+                    pool.rewind_to_mark();
+                    model.truncate(marked);
+                }
+            }
+            _ => {
+                pool.rewind();
+                model.clear();
+            }
+        }
+        max_len = max_len.max(model.len());
+        cycles += 1;
+
+        if cycles.is_multiple_of(64) {
+            check(&pool, &model);
+            // Rewound blocks are reused, so the pool never holds more blocks
+            // than its largest contents needed.
+            assert!(pool.block_count() <= max_len.div_ceil(CAP) + 1);
+        }
+        if last_report.elapsed() >= Duration::from_secs(10) {
+            last_report = Instant::now();
+            println!(
+                "{:>8.0?} cycles={cycles} len={} blocks={} rss={}",
+                start.elapsed(),
+                model.len(),
+                pool.block_count(),
+                rss_bytes().map_or("n/a".to_string(), |rss| format!("{} KiB", rss / 1024)),
+            );
+        }
+    }
+    check(&pool, &model);
+    println!("soak finished: {cycles} cycles, max len {max_len}");
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let seconds = args.next().map_or(60, |arg| arg.parse().expect("seconds"));
+    let seed = args.next().map_or(0x5eed, |arg| arg.parse().expect("seed"));
+    soak(Duration::from_secs(seconds), seed, |x| x as f64);
+}
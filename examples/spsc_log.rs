@@ -0,0 +1,95 @@
+//! A single-producer, single-consumer log buffer. The producer appends
+//! records to a `SmallObjectPool`, the consumer reads new records through a
+//! `Tail` and releases every block it has fully read with `discard_before`,
+//! so memory use follows the distance between the two rather than the
+//! number of records ever logged.
+//!
+//! ```text
+//! cargo run --example spsc_log -- [records]
+//! ```
+
+use smallobjectpool::{smallobjectpool::SmallObjectPool, tail::Tail};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Debug,
+    Info,
+    Warn,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Record {
+    sequence: u64,
+    level: Level,
+    code: u32,
+}
+
+type Log = SmallObjectPool<Record, 128>;
+
+struct Consumer {
+    tail: Tail<Record, 128>,
+    next_sequence: u64,
+    warnings: usize,
+    checksum: u64,
+}
+
+impl Consumer {
+    fn new(log: &Log) -> Self {
+        Consumer {
+            tail: log.subscribe(),
+            next_sequence: 0,
+            warnings: 0,
+            checksum: 0,
+        }
+    }
+
+    /// Read the records logged since the last drain, then free the blocks
+    /// behind them.
+    fn drain(&mut self, log: &mut Log) -> usize {
+        let mut read = 0;
+        for record in self.tail.poll(log).expect("the tail reads this log") {
+            assert_eq!(record.sequence, self.next_sequence, "record lost");
+            self.next_sequence += 1;
+            self.warnings += usize::from(record.level == Level::Warn);
+            self.checksum += u64::from(record.code);
+            read += 1;
+        }
+        log.discard_before(self.tail.position());
+        read
+    }
+}
+
+fn main() {
+    let records: u64 = std::env::args()
+        .nth(1)
+        .map_or(100_000, |arg| arg.parse().expect("records"));
+    let mut log = Log::new();
+    let mut consumer = Consumer::new(&log);
+    let mut most_blocks = 0;
+    let mut burst = 1;
+    let mut sequence = 0;
+    while sequence < records {
+        // The producer logs bursts of varying length between two drains.
+        for _ in 0..burst.min(records - sequence) {
+            let level = match sequence % 10 {
+                0 => Level::Warn,
+                1..=4 => Level::Info,
+                _ => Level::Debug,
+            };
+            log.push(Record {
+                sequence,
+                level,
+                code: (sequence * 7 % 1000) as u32,
+            });
+            sequence += 1;
+        }
+        most_blocks = most_blocks.max(log.block_count());
+        consumer.drain(&mut log);
+        burst = burst * 3 % 1000 + 1;
+    }
+    assert_eq!(consumer.next_sequence, records);
+    println!(
+        "{records} records, {} warnings, checksum {}, at most {most_blocks} blocks resident",
+        consumer.warnings, consumer.checksum
+    );
+}
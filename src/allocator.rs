@@ -0,0 +1,46 @@
+use std::{
+    alloc::{AllocError, Allocator, Layout},
+    ptr::NonNull,
+};
+
+use crate::rawpool::RawPool;
+
+/// Bump allocation out of a `RawPool`, so that `Vec`, `Box` and friends can
+/// live inside the pool. Deallocation is a no-op: memory is reclaimed when
+/// the pool is rewound, which requires every borrowing collection to be gone.
+unsafe impl<const CAP: usize> Allocator for &RawPool<CAP> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.try_alloc(layout).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocator_vec_in_pool() {
+        let mut pool = RawPool::<1024>::new();
+        pool.mark();
+        {
+            let mut vec = Vec::new_in(&pool);
+            for i in 0..100u32 {
+                vec.push(i);
+            }
+            assert_eq!(vec.iter().sum::<u32>(), 4950);
+            let boxed = Box::new_in(3.5f64, &pool);
+            assert_eq!(*boxed, 3.5);
+        }
+        pool.rewind_to_mark();
+    }
+
+    #[test]
+    fn test_allocator_too_large() {
+        let pool = RawPool::<64>::new();
+        let mut vec: Vec<u8, _> = Vec::new_in(&pool);
+        assert!(vec.try_reserve(65).is_err());
+    }
+}
@@ -1,29 +1,44 @@
-use std::{mem::MaybeUninit, ptr::NonNull};
+use std::{
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::ptrbased::PtrBased;
 
 pub struct ArrayLike<T, const CAP: usize> {
     data: [MaybeUninit<T>; CAP],
-    current_ptr: Option<NonNull<T>>,
+    /// Number of used slots, kept as a count so that the block can be moved.
+    len: usize,
+    /// Slots handed out uninitialized by the pool and not committed since.
+    reserved: Vec<usize>,
+    /// Tells the block apart from every other one created by the process,
+    /// including later blocks at the same address.
+    serial: u64,
+    /// Generation of each slot, bumped by the pool when the element in it is
+    /// rewound or replaced. Only grown once the pool hands out handles.
+    generations: Vec<u64>,
 }
 
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(1);
+
 impl<T: Clone + Copy, const CAP: usize> ArrayLike<T, CAP> {
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.data.iter().map(|x| unsafe { x.assume_init() })
+    }
+}
+
+impl<T, const CAP: usize> ArrayLike<T, CAP> {
     pub fn new() -> Self {
         ArrayLike {
             data: [const { MaybeUninit::uninit() }; CAP],
-            current_ptr: None,
+            len: 0,
+            reserved: Vec::new(),
+            serial: NEXT_SERIAL.fetch_add(1, Ordering::Relaxed),
+            generations: Vec::new(),
         }
     }
 
-    /// Helper function to initialize the current pointer.
-    fn init(&mut self) {
-        self.current_ptr = self.begin();
-    }
-
-    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
-        self.data.iter().map(|x| unsafe { x.assume_init() })
-    }
-
     /// Insert a value at the given index.
     pub fn insert(&mut self, index: usize, value: T) -> Option<()> {
         if index < CAP {
@@ -38,6 +53,9 @@ impl<T: Clone + Copy, const CAP: usize> ArrayLike<T, CAP> {
     }
 
     /// Insert a value at the given index without bounds checking.
+    ///
+    /// # Safety
+    /// `index` must be smaller than `CAP`.
     pub unsafe fn insert_unchecked(&mut self, index: usize, value: T) {
         let ptr = self.data.as_mut_ptr().add(index) as *mut T;
         ptr.write(value);
@@ -45,42 +63,157 @@ impl<T: Clone + Copy, const CAP: usize> ArrayLike<T, CAP> {
 
     /// Try to push a value into the block.
     pub fn try_push(&mut self, value: T) -> Option<()> {
-        if self.current_ptr.is_none() {
-            self.init();
-        }
-        if self.current_ptr >= self.end() {
-            None
-        } else {
-            unsafe {
-                self.current_ptr.unwrap().as_ptr().write(value);
-                self.current_ptr = self.next(self.current_ptr.unwrap());
-            }
-            Some(())
-        }
+        unsafe { self.try_push_and_get_ptr(value) }.map(|_| ())
     }
 
     /// Push a value into the block and return a pointer to the pushed value.
+    ///
+    /// # Safety
+    /// The returned pointer is only valid while the block is alive.
     pub unsafe fn try_push_and_get_ptr(&mut self, value: T) -> Option<NonNull<T>> {
-        if self.current_ptr.is_none() {
-            self.init();
+        if self.len == CAP {
+            return None;
         }
-        if self.current_ptr >= self.end() {
-            None
-        } else {
-            self.current_ptr.unwrap().as_ptr().write(value);
-            let ptr = self.current_ptr.unwrap();
-            self.current_ptr = self.next(self.current_ptr.unwrap());
-            Some(ptr)
+        let slot = self.data[self.len].write(value);
+        self.len += 1;
+        Some(NonNull::from(slot))
+    }
+
+    /// Number of slots before the block's cursor.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of slots before the block's cursor that have been written,
+    /// i.e. not reserved uninitialized and left uncommitted.
+    pub fn initialized_len(&self) -> usize {
+        self.len() - self.reserved_before(self.len())
+    }
+
+    /// Number of reserved, uncommitted slots among the first `len`.
+    pub(crate) fn reserved_before(&self, len: usize) -> usize {
+        self.reserved.iter().filter(|&&slot| slot < len).count()
+    }
+
+    /// Record that the `n` slots from `slot` on were handed out uninitialized.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) fn reserve_uninit(&mut self, slot: usize, n: usize) {
+        self.reserved.extend(slot..slot + n);
+    }
+
+    /// Record that the `n` slots from `slot` on have been written, returning
+    /// whether all of them were reserved.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) fn commit_uninit(&mut self, slot: usize, n: usize) -> bool {
+        let before = self.reserved.len();
+        self.reserved
+            .retain(|reserved| !(slot..slot + n).contains(reserved));
+        before - self.reserved.len() == n
+    }
+
+    /// Generation of `slot`, see `bump_generations`.
+    pub(crate) fn generation(&self, slot: usize) -> u64 {
+        self.generations.get(slot).copied().unwrap_or(0)
+    }
+
+    /// Start a new generation for the slots in `slots`, whose elements have
+    /// been rewound or replaced.
+    pub(crate) fn bump_generations(&mut self, slots: std::ops::Range<usize>) {
+        if self.generations.len() < slots.end {
+            self.generations.resize(slots.end, 0);
+        }
+        for generation in &mut self.generations[slots] {
+            *generation += 1;
         }
     }
+
+    /// Forget the reservations from `slot` on, once they have been rewound.
+    pub(crate) fn forget_reserved_from(&mut self, slot: usize) {
+        self.reserved.retain(|&reserved| reserved < slot);
+    }
+
+    /// The slots after the block's cursor, to be written in place and then
+    /// committed with `commit`.
+    pub fn raw_slots_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        &mut self.data[self.len..]
+    }
+
+    /// Move the block's cursor over the first `n` slots of `raw_slots_mut`.
+    ///
+    /// # Safety
+    /// The `n` slots must be initialized.
+    ///
+    /// # Panics
+    /// If `n` is larger than the number of slots after the cursor.
+    pub unsafe fn commit(&mut self, n: usize) {
+        assert!(
+            n <= CAP - self.len,
+            "cannot commit {n} slots past {}",
+            self.len
+        );
+        self.len += n;
+    }
+
+    /// Move the block's cursor so that the first `len` slots count as used.
+    ///
+    /// # Safety
+    /// `len` must not exceed `CAP` and the first `len` slots must be initialized.
+    pub unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    /// First slot of the block behind `block`, reached without borrowing the
+    /// block, so that it can be written through while references to other
+    /// slots are alive.
+    ///
+    /// # Safety
+    /// `block` must point to a live block.
+    pub(crate) unsafe fn slots(block: NonNull<Self>) -> NonNull<T> {
+        NonNull::new_unchecked(ptr::addr_of_mut!((*block.as_ptr()).data).cast())
+    }
+
+    /// `len` of the block behind `block`, without borrowing its slots.
+    ///
+    /// # Safety
+    /// `block` must point to a live block.
+    pub(crate) unsafe fn len_of(block: NonNull<Self>) -> usize {
+        ptr::addr_of!((*block.as_ptr()).len).read()
+    }
+
+    /// `serial` of the block behind `block`, never 0.
+    ///
+    /// # Safety
+    /// `block` must point to a live block.
+    pub(crate) unsafe fn serial_of(block: NonNull<Self>) -> u64 {
+        ptr::addr_of!((*block.as_ptr()).serial).read()
+    }
+
+    /// `set_len` on the block behind `block`, without borrowing its slots.
+    ///
+    /// # Safety
+    /// As for `set_len`, and `block` must point to a live block.
+    pub(crate) unsafe fn set_len_of(block: NonNull<Self>, len: usize) {
+        ptr::addr_of_mut!((*block.as_ptr()).len).write(len);
+    }
 }
 
+impl<T, const CAP: usize> Default for ArrayLike<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drops the slots before the block's cursor; the others may be uninitialized.
 impl<T, const CAP: usize> Drop for ArrayLike<T, CAP> {
     fn drop(&mut self) {
-        for i in 0..CAP {
-            unsafe {
-                self.data.as_mut_ptr().add(i).drop_in_place();
-            }
+        let len = self.len();
+        unsafe {
+            std::ptr::slice_from_raw_parts_mut(self.data.as_mut_ptr() as *mut T, len)
+                .drop_in_place();
         }
     }
 }
@@ -188,6 +321,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_array_like_len() {
+        let mut list: ArrayLike<i32, 8> = ArrayLike::new();
+        assert!(list.is_empty());
+        list.try_push(1).unwrap();
+        list.try_push(2).unwrap();
+        assert_eq!(list.len(), 2);
+        unsafe { list.set_len(5) };
+        assert_eq!(list.len(), 5);
+    }
+
+    #[test]
+    fn test_array_like_initialized_len() {
+        let mut list: ArrayLike<i32, 8> = ArrayLike::new();
+        list.try_push(1).unwrap();
+        list.reserve_uninit(1, 3);
+        unsafe { list.set_len(4) };
+        assert_eq!(list.initialized_len(), 1);
+        assert!(list.commit_uninit(1, 2));
+        assert_eq!(list.initialized_len(), 3);
+        assert!(!list.commit_uninit(2, 2));
+        assert_eq!(list.initialized_len(), 4);
+    }
+
+    #[test]
+    fn test_array_like_raw_slots() {
+        let mut list: ArrayLike<u8, 8> = ArrayLike::new();
+        list.try_push(1).unwrap();
+        let slots = list.raw_slots_mut();
+        assert_eq!(slots.len(), 7);
+        slots[0].write(2);
+        slots[1].write(3);
+        unsafe { list.commit(2) };
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.iter().take(3).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
     #[test]
     fn test_array_like_try_push() {
         let mut list: ArrayLike<i32, 100> = ArrayLike::new();
@@ -195,4 +365,18 @@ mod tests {
             list.try_push(i).unwrap();
         }
     }
+
+    #[test]
+    fn test_array_like_drops_used_slots() {
+        let drops = std::rc::Rc::new(());
+        let mut list: ArrayLike<std::rc::Rc<()>, 8> = ArrayLike::new();
+        for _ in 0..3 {
+            list.try_push(drops.clone()).unwrap();
+        }
+        assert_eq!(std::rc::Rc::strong_count(&drops), 4);
+        // The block can be moved once it holds values.
+        let list = Box::new(list);
+        drop(list);
+        assert_eq!(std::rc::Rc::strong_count(&drops), 1);
+    }
 }
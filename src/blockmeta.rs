@@ -0,0 +1,76 @@
+use std::ops::{Bound, RangeBounds};
+
+/// # BlockMeta
+/// User data attached to every block of a pool, updated as values are pushed.
+/// A block's meta is reset to `Default` whenever the pool starts writing it
+/// from its first slot again; rewinding into the middle of a block keeps the
+/// meta as is, so it may describe a superset of the block's live values.
+pub trait BlockMeta<T>: Default {
+    fn record(&mut self, value: &T);
+}
+
+impl<T> BlockMeta<T> for () {
+    fn record(&mut self, _value: &T) {}
+}
+
+/// # MinMax
+/// Smallest and largest value pushed to a block, or `None` for an empty block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MinMax<T>(pub Option<(T, T)>);
+
+impl<T> Default for MinMax<T> {
+    fn default() -> Self {
+        MinMax(None)
+    }
+}
+
+impl<T: Copy + PartialOrd> BlockMeta<T> for MinMax<T> {
+    fn record(&mut self, value: &T) {
+        self.0 = match self.0 {
+            Some((min, max)) => Some((
+                if *value < min { *value } else { min },
+                if *value > max { *value } else { max },
+            )),
+            None => Some((*value, *value)),
+        };
+    }
+}
+
+impl<T: PartialOrd> MinMax<T> {
+    /// Whether some value between the block's min and max could fall in `range`.
+    pub fn overlaps(&self, range: &impl RangeBounds<T>) -> bool {
+        let Some((min, max)) = &self.0 else {
+            return false;
+        };
+        let above_start = match range.start_bound() {
+            Bound::Included(start) => max >= start,
+            Bound::Excluded(start) => max > start,
+            Bound::Unbounded => true,
+        };
+        let below_end = match range.end_bound() {
+            Bound::Included(end) => min <= end,
+            Bound::Excluded(end) => min < end,
+            Bound::Unbounded => true,
+        };
+        above_start && below_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max_record() {
+        let mut meta = MinMax::default();
+        assert!(!meta.overlaps(&..));
+        for value in [3, 1, 4, 1, 5] {
+            meta.record(&value);
+        }
+        assert_eq!(meta, MinMax(Some((1, 5))));
+        assert!(meta.overlaps(&(5..)));
+        assert!(!meta.overlaps(&(6..)));
+        assert!(meta.overlaps(&(..=1)));
+        assert!(!meta.overlaps(&(..1)));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{
+    blockmeta::BlockMeta,
+    growth::Growth,
+    policy::{self, Violation},
+    smallobjectpool::SmallObjectPool,
+};
+
+/// # BlockPool
+/// The push / mark / rewind interface shared by the pools, so that layers
+/// such as `InstrumentedPool` and `CheckedPool` can wrap any of them and
+/// each other.
+pub trait BlockPool {
+    type Item;
+
+    fn push(&mut self, value: Self::Item);
+
+    fn mark(&mut self);
+
+    fn rewind(&mut self);
+
+    fn rewind_to_mark(&mut self);
+
+    fn block_count(&self) -> usize;
+
+    /// Check the pool's internal invariants. A broken one is a violation
+    /// under the crate's `Policy`.
+    fn validate(&self) -> Result<(), Violation>;
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> BlockPool for SmallObjectPool<T, CAP, M, G> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        SmallObjectPool::push(self, value)
+    }
+
+    fn mark(&mut self) {
+        SmallObjectPool::mark(self)
+    }
+
+    fn rewind(&mut self) {
+        SmallObjectPool::rewind(self)
+    }
+
+    fn rewind_to_mark(&mut self) {
+        SmallObjectPool::rewind_to_mark(self)
+    }
+
+    fn block_count(&self) -> usize {
+        SmallObjectPool::block_count(self)
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        SmallObjectPool::validate(self)
+    }
+}
+
+/// An operation performed on a pool, as reported by `InstrumentedPool`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    Push,
+    Mark,
+    Rewind,
+    RewindToMark,
+}
+
+/// Number of operations of each kind an `InstrumentedPool` has seen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolCounters {
+    pub pushes: usize,
+    pub marks: usize,
+    pub rewinds: usize,
+    pub rewinds_to_mark: usize,
+}
+
+/// # InstrumentedPool
+/// Counts the operations performed on the wrapped pool and, if a tracer is
+/// set, reports each of them to it.
+pub struct InstrumentedPool<P> {
+    inner: P,
+    counters: PoolCounters,
+    tracer: Option<Box<dyn FnMut(PoolEvent)>>,
+}
+
+impl<P: BlockPool> InstrumentedPool<P> {
+    pub fn new(inner: P) -> Self {
+        InstrumentedPool {
+            inner,
+            counters: PoolCounters::default(),
+            tracer: None,
+        }
+    }
+
+    /// Report every operation to `tracer` as it happens.
+    pub fn with_tracer(mut self, tracer: impl FnMut(PoolEvent) + 'static) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    pub fn counters(&self) -> PoolCounters {
+        self.counters
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn record(&mut self, event: PoolEvent) {
+        let counter = match event {
+            PoolEvent::Push => &mut self.counters.pushes,
+            PoolEvent::Mark => &mut self.counters.marks,
+            PoolEvent::Rewind => &mut self.counters.rewinds,
+            PoolEvent::RewindToMark => &mut self.counters.rewinds_to_mark,
+        };
+        *counter += 1;
+        if let Some(tracer) = &mut self.tracer {
+            tracer(event);
+        }
+    }
+}
+
+impl<P: BlockPool> BlockPool for InstrumentedPool<P> {
+    type Item = P::Item;
+
+    fn push(&mut self, value: P::Item) {
+        self.inner.push(value);
+        self.record(PoolEvent::Push);
+    }
+
+    fn mark(&mut self) {
+        self.inner.mark();
+        self.record(PoolEvent::Mark);
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+        self.record(PoolEvent::Rewind);
+    }
+
+    fn rewind_to_mark(&mut self) {
+        self.inner.rewind_to_mark();
+        self.record(PoolEvent::RewindToMark);
+    }
+
+    fn block_count(&self) -> usize {
+        self.inner.block_count()
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        self.inner.validate()
+    }
+}
+
+/// # CheckedPool
+/// Validates the wrapped pool after every operation, so that a broken
+/// invariant is caught where it is introduced rather than where it is
+/// noticed. The operations can't return the violation, so under
+/// `Policy::Error` they panic.
+pub struct CheckedPool<P> {
+    inner: P,
+}
+
+impl<P: BlockPool> CheckedPool<P> {
+    pub fn new(inner: P) -> Self {
+        let pool = CheckedPool { inner };
+        pool.check();
+        pool
+    }
+
+    #[track_caller]
+    fn check(&self) {
+        if let Err(violation) = self.inner.validate() {
+            policy::fail(format_args!("{violation}"));
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: BlockPool> BlockPool for CheckedPool<P> {
+    type Item = P::Item;
+
+    fn push(&mut self, value: P::Item) {
+        self.inner.push(value);
+        self.check();
+    }
+
+    fn mark(&mut self) {
+        self.inner.mark();
+        self.check();
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+        self.check();
+    }
+
+    fn rewind_to_mark(&mut self) {
+        self.inner.rewind_to_mark();
+        self.check();
+    }
+
+    fn block_count(&self) -> usize {
+        self.inner.block_count()
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        self.inner.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_stacked_layers() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let traced = events.clone();
+        let pool = InstrumentedPool::new(SmallObjectPool::<u32, 4>::new())
+            .with_tracer(move |event| traced.borrow_mut().push(event));
+        let mut pool = CheckedPool::new(pool);
+        pool.mark();
+        for i in 0..6 {
+            pool.push(i);
+        }
+        pool.rewind_to_mark();
+        pool.push(1);
+        pool.rewind();
+        assert_eq!(pool.block_count(), 2);
+        assert_eq!(
+            pool.inner().counters(),
+            PoolCounters {
+                pushes: 7,
+                marks: 1,
+                rewinds: 1,
+                rewinds_to_mark: 1,
+            }
+        );
+        assert_eq!(events.borrow().len(), 10);
+        assert_eq!(events.borrow()[7], PoolEvent::RewindToMark);
+        let pool = pool.into_inner().into_inner();
+        assert_eq!(pool.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_stacked_layers_non_copy() {
+        let mut pool = CheckedPool::new(InstrumentedPool::new(SmallObjectPool::<String, 4>::new()));
+        pool.mark();
+        for i in 0..5 {
+            pool.push(i.to_string());
+        }
+        pool.rewind_to_mark();
+        assert_eq!(pool.inner().counters().pushes, 5);
+        let pool = pool.into_inner().into_inner();
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.block_count(), 2);
+    }
+}
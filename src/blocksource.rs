@@ -0,0 +1,156 @@
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    mem::ManuallyDrop,
+    ptr::NonNull,
+};
+
+use crate::smallobjectpool::SmallObjectPool;
+
+/// # BlockMemory
+/// An owned, untyped block allocation, freed when dropped unless a pool
+/// adopts it.
+pub struct BlockMemory {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// The block is an owned allocation like a `Box<[MaybeUninit<u8>]>`.
+unsafe impl Send for BlockMemory {}
+unsafe impl Sync for BlockMemory {}
+
+impl BlockMemory {
+    /// Allocate a block for `layout`, which must not be zero-sized.
+    pub fn new(layout: Layout) -> Self {
+        debug_assert!(layout.size() > 0, "blocks can't be zero-sized");
+        match NonNull::new(unsafe { alloc(layout) }) {
+            Some(ptr) => BlockMemory { ptr, layout },
+            None => handle_alloc_error(layout),
+        }
+    }
+
+    /// Take ownership of an allocation.
+    ///
+    /// # Safety
+    /// `ptr` must have been allocated by the global allocator with `layout`.
+    pub unsafe fn from_raw(ptr: NonNull<u8>, layout: Layout) -> Self {
+        BlockMemory { ptr, layout }
+    }
+
+    /// Give up ownership of the allocation and the layout it must be freed with.
+    pub fn into_raw(self) -> (NonNull<u8>, Layout) {
+        let block = ManuallyDrop::new(self);
+        (block.ptr, block.layout)
+    }
+
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Whether the block is large and aligned enough to hold `layout`.
+    pub fn fits(&self, layout: Layout) -> bool {
+        self.layout.size() >= layout.size() && self.layout.align() >= layout.align()
+    }
+}
+
+impl Drop for BlockMemory {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+/// # BlockSource
+/// A pool that can give away the blocks it holds but isn't using, and adopt
+/// blocks given away by others, so pools used in different phases of a
+/// program can share one set of blocks instead of each caching its own.
+pub trait BlockSource {
+    /// Layout a block must fit to be adopted.
+    fn block_layout(&self) -> Layout;
+
+    /// Give away one block that holds nothing live, if there is one.
+    fn take_free_block(&mut self) -> Option<BlockMemory>;
+
+    /// Adopt `block` as a free block, handing it back if it doesn't fit
+    /// `block_layout`.
+    fn add_free_block(&mut self, block: BlockMemory) -> Result<(), BlockMemory>;
+
+    /// Move every free block that `to` can adopt over to it, returning how
+    /// many were moved.
+    fn donate_free_blocks(&mut self, to: &mut impl BlockSource) -> usize {
+        let mut count = 0;
+        while let Some(block) = self.take_free_block() {
+            if let Err(block) = to.add_free_block(block) {
+                // The block was just ours, so it fits again.
+                let _ = self.add_free_block(block);
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+}
+
+/// # BlockRecycler
+/// A cache of free blocks of one layout. Pools give their blocks to it with
+/// `recycle` when they are done and take them back with `with_capacity_from`,
+/// so a program that creates and drops many pools of the same element type
+/// doesn't allocate and free a block for each of them. At most `limit` blocks
+/// are kept; the rest are freed.
+pub struct BlockRecycler {
+    layout: Layout,
+    limit: usize,
+    blocks: Vec<BlockMemory>,
+}
+
+impl BlockRecycler {
+    /// Cache any number of blocks that fit `layout`.
+    pub fn new(layout: Layout) -> Self {
+        Self::with_limit(layout, usize::MAX)
+    }
+
+    /// Cache at most `limit` blocks that fit `layout`.
+    pub fn with_limit(layout: Layout, limit: usize) -> Self {
+        BlockRecycler {
+            layout,
+            limit,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Cache blocks for `SmallObjectPool<T, CAP>`s, with the layout the pools
+    /// allocate their blocks with.
+    pub fn for_pool<T, const CAP: usize>() -> Self {
+        Self::new(SmallObjectPool::<T, CAP>::alloc_layout())
+    }
+
+    /// Number of blocks cached.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl BlockSource for BlockRecycler {
+    fn block_layout(&self) -> Layout {
+        self.layout
+    }
+
+    fn take_free_block(&mut self) -> Option<BlockMemory> {
+        self.blocks.pop()
+    }
+
+    /// Cache `block`, handing it back if it doesn't fit or the cache is full.
+    fn add_free_block(&mut self, block: BlockMemory) -> Result<(), BlockMemory> {
+        if !block.fits(self.layout) || self.blocks.len() >= self.limit {
+            return Err(block);
+        }
+        self.blocks.push(block);
+        Ok(())
+    }
+}
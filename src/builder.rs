@@ -0,0 +1,65 @@
+use std::marker::PhantomData;
+
+use crate::{blockmeta::BlockMeta, smallobjectpool::SmallObjectPool};
+
+/// # PoolBuilder
+/// Configures a `SmallObjectPool` before it is created.
+///
+/// By default the first block is allocated on the first push, so pools that
+/// are never used cost nothing. With `eager_first_block(true)` it is allocated
+/// up front instead, which moves the allocation out of the first push at the
+/// price of holding a block even if nothing is ever pushed.
+pub struct PoolBuilder<T, const CAP: usize, M = ()> {
+    eager_first_block: bool,
+    _marker: PhantomData<(T, M)>,
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>> PoolBuilder<T, CAP, M> {
+    pub fn new() -> Self {
+        PoolBuilder {
+            eager_first_block: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocate the first block when the pool is built rather than on the first push.
+    pub fn eager_first_block(mut self, eager: bool) -> Self {
+        self.eager_first_block = eager;
+        self
+    }
+
+    pub fn build(self) -> SmallObjectPool<T, CAP, M> {
+        let mut pool = SmallObjectPool::new();
+        if self.eager_first_block {
+            pool.allocate_first_block();
+        }
+        pool
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>> Default for PoolBuilder<T, CAP, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_lazy() {
+        let pool = PoolBuilder::<u32, 4>::new().build();
+        assert!(pool.blocks().is_empty());
+    }
+
+    #[test]
+    fn test_builder_eager() {
+        let mut pool = PoolBuilder::<u32, 4>::new().eager_first_block(true).build();
+        let first = pool.blocks().first().copied();
+        assert!(first.is_some());
+        pool.push(1);
+        assert_eq!(pool.blocks().first().copied(), first);
+        assert_eq!(pool.blocks().last().copied(), first);
+    }
+}
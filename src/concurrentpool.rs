@@ -0,0 +1,236 @@
+use std::{
+    cell::UnsafeCell,
+    mem::{needs_drop, MaybeUninit},
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// A block of a `ConcurrentPool`, with its own bump cursor.
+struct Block<T, const CAP: usize> {
+    slots: [UnsafeCell<MaybeUninit<T>>; CAP],
+    /// Number of slots handed out, which goes past `CAP` as pushes find the
+    /// block full.
+    reserved: AtomicUsize,
+}
+
+impl<T, const CAP: usize> Block<T, CAP> {
+    fn alloc() -> NonNull<Self> {
+        let mut block = Box::<Self>::new_uninit();
+        // The slots start uninitialized, only the cursor needs a value.
+        unsafe {
+            ptr::addr_of_mut!((*block.as_mut_ptr()).reserved).write(AtomicUsize::new(0));
+            NonNull::from(Box::leak(block.assume_init()))
+        }
+    }
+
+    /// Number of slots holding an element, once every push has returned.
+    fn len(&mut self) -> usize {
+        (*self.reserved.get_mut()).min(CAP)
+    }
+}
+
+/// The blocks of a `ConcurrentPool` and the index of the one being filled.
+struct Blocks<T, const CAP: usize> {
+    blocks: Vec<NonNull<Block<T, CAP>>>,
+    current: usize,
+}
+
+/// # ConcurrentPool
+/// An append-only pool that several threads can push to at once, for example
+/// to record a tape from parallel workers. Each block has an atomic bump
+/// cursor, so a push only takes a lock when its block is full and the pool
+/// moves to the next one. Elements never move, so the references `push`
+/// returns stay valid until the pool is rewound or dropped. Elements pushed
+/// by different threads are interleaved in the order their slots were taken.
+pub struct ConcurrentPool<T, const CAP: usize> {
+    blocks: Mutex<Blocks<T, CAP>>,
+    current: AtomicPtr<Block<T, CAP>>,
+}
+
+// Elements are pushed from any thread and dropped by the owner, and
+// references to them are shared between the pushing threads.
+unsafe impl<T: Send, const CAP: usize> Send for ConcurrentPool<T, CAP> {}
+unsafe impl<T: Send + Sync, const CAP: usize> Sync for ConcurrentPool<T, CAP> {}
+
+impl<T, const CAP: usize> ConcurrentPool<T, CAP> {
+    /// Create an empty pool. No block is allocated until the first push.
+    pub fn new() -> Self {
+        assert!(CAP > 0, "blocks need room for at least one element");
+        ConcurrentPool {
+            blocks: Mutex::new(Blocks {
+                blocks: Vec::new(),
+                current: 0,
+            }),
+            current: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Push `value` and return a reference to it, from any thread.
+    pub fn push(&self, value: T) -> &T {
+        loop {
+            let block = self.current.load(Ordering::Acquire);
+            if let Some(block) = unsafe { block.as_ref() } {
+                let index = block.reserved.fetch_add(1, Ordering::Relaxed);
+                if index < CAP {
+                    // The cursor hands every slot to a single push.
+                    let slot = unsafe { &mut *block.slots[index].get() };
+                    return slot.write(value);
+                }
+            }
+            self.advance(block);
+        }
+    }
+
+    /// Move to the block after `full`, unless another push already has.
+    fn advance(&self, full: *mut Block<T, CAP>) {
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        if self.current.load(Ordering::Relaxed) != full {
+            return;
+        }
+        let next = if full.is_null() {
+            0
+        } else {
+            blocks.current + 1
+        };
+        if next == blocks.blocks.len() {
+            blocks.blocks.push(Block::alloc());
+        }
+        blocks.current = next;
+        self.current
+            .store(blocks.blocks[next].as_ptr(), Ordering::Release);
+    }
+
+    fn blocks_mut(&mut self) -> &mut Blocks<T, CAP> {
+        self.blocks.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of blocks held by the pool
+    pub fn block_count(&mut self) -> usize {
+        self.blocks_mut().blocks.len()
+    }
+
+    /// Number of elements pushed since the start of the pool. Takes the pool
+    /// exclusively so that no push is in flight and the count is exact.
+    pub fn len(&mut self) -> usize {
+        self.blocks_mut()
+            .blocks
+            .iter()
+            .map(|block| unsafe { (*block.as_ptr()).len() })
+            .sum()
+    }
+
+    /// Whether nothing has been pushed since the start of the pool. Takes
+    /// the pool exclusively for the same reason as `len`.
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the elements, block by block. Taking the pool exclusively
+    /// ensures every push has returned.
+    pub fn iter(&mut self) -> impl Iterator<Item = &T> {
+        self.iter_mut().map(|value| &*value)
+    }
+
+    /// Mutably iterate over the elements, block by block.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.blocks_mut().blocks.iter().flat_map(|block| {
+            // Each block is borrowed once, through the exclusive borrow of the pool.
+            let block = unsafe { &mut *block.as_ptr() };
+            let len = block.len();
+            block.slots[..len]
+                .iter_mut()
+                .map(|slot| unsafe { slot.get_mut().assume_init_mut() })
+        })
+    }
+
+    /// Drop every element, keeping the blocks for reuse.
+    pub fn rewind(&mut self) {
+        let blocks = self.blocks.get_mut().unwrap_or_else(|e| e.into_inner());
+        for &block in &blocks.blocks {
+            let block = unsafe { &mut *block.as_ptr() };
+            let len = block.len();
+            *block.reserved.get_mut() = 0;
+            if needs_drop::<T>() {
+                for slot in &mut block.slots[..len] {
+                    unsafe { slot.get_mut().assume_init_drop() };
+                }
+            }
+        }
+        blocks.current = 0;
+        let first = blocks
+            .blocks
+            .first()
+            .map_or(ptr::null_mut(), |block| block.as_ptr());
+        *self.current.get_mut() = first;
+    }
+}
+
+impl<T, const CAP: usize> Default for ConcurrentPool<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize> Drop for ConcurrentPool<T, CAP> {
+    fn drop(&mut self) {
+        self.rewind();
+        for block in self.blocks_mut().blocks.drain(..) {
+            drop(unsafe { Box::from_raw(block.as_ptr()) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_concurrentpool_parallel_push() {
+        let mut pool = ConcurrentPool::<(usize, usize), 16>::new();
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let pool = &pool;
+                scope.spawn(move || {
+                    let pushed: Vec<&(usize, usize)> =
+                        (0..1000).map(|i| pool.push((worker, i))).collect();
+                    for (i, value) in pushed.into_iter().enumerate() {
+                        assert_eq!(*value, (worker, i));
+                    }
+                });
+            }
+        });
+        assert_eq!(pool.len(), 4000);
+        let mut next = [0; 4];
+        for &(worker, i) in pool.iter() {
+            assert_eq!(i, next[worker]);
+            next[worker] += 1;
+        }
+        assert_eq!(next, [1000; 4]);
+        assert_eq!(pool.block_count(), 250);
+    }
+
+    #[test]
+    fn test_concurrentpool_rewind_reuses_blocks() {
+        let value = Arc::new(());
+        let mut pool = ConcurrentPool::<Arc<()>, 4>::new();
+        assert!(pool.is_empty());
+        for _ in 0..10 {
+            pool.push(value.clone());
+        }
+        assert_eq!((pool.block_count(), Arc::strong_count(&value)), (3, 11));
+        pool.rewind();
+        assert_eq!((pool.len(), Arc::strong_count(&value)), (0, 1));
+        for _ in 0..6 {
+            pool.push(value.clone());
+        }
+        pool.iter_mut().for_each(|value| *value = Arc::new(()));
+        assert_eq!((pool.len(), pool.block_count()), (6, 3));
+        assert_eq!(Arc::strong_count(&value), 1);
+        drop(pool);
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    marker::PhantomData,
+    mem::needs_drop,
+    ptr::NonNull,
+};
+
+use crate::{
+    blockpool::BlockPool,
+    blocksource::BlockMemory,
+    growth::GrowthStrategy,
+    policy::{ensure, Violation},
+};
+
+/// # DynPool
+/// A pool like `SmallObjectPool` whose block capacity is chosen when it is
+/// created rather than in its type, for tuning the block size per workload.
+/// Elements fill every block before the next one is used, so the position
+/// of the cursor is simply the number of elements pushed since the start.
+/// Blocks may grow in size as the pool does, see `GrowthStrategy`.
+pub struct DynPool<T> {
+    blocks: UnsafeCell<Vec<BlockMemory>>,
+    starts: UnsafeCell<Vec<usize>>,
+    block_capacity: usize,
+    strategy: GrowthStrategy,
+    len: Cell<usize>,
+    marked: usize,
+    _marker: PhantomData<T>,
+}
+
+// The pool owns its blocks and their elements. It isn't `Sync`: `alloc` moves
+// the cursor through a shared borrow.
+unsafe impl<T: Send> Send for DynPool<T> {}
+
+impl<T> DynPool<T> {
+    /// Create an empty pool of blocks of `block_capacity` elements. No block
+    /// is allocated until the first push.
+    ///
+    /// # Panics
+    /// If `block_capacity` is zero, `T` is zero-sized or a block's size
+    /// overflows `isize`.
+    pub fn new(block_capacity: usize) -> Self {
+        Self::with_strategy(block_capacity, GrowthStrategy::Fixed)
+    }
+
+    /// Create an empty pool whose first block holds `block_capacity`
+    /// elements and whose next blocks are sized by `strategy`.
+    ///
+    /// # Panics
+    /// As for `new`, or if a doubling strategy's `max` is below `block_capacity`.
+    pub fn with_strategy(block_capacity: usize, strategy: GrowthStrategy) -> Self {
+        assert!(
+            block_capacity > 0,
+            "blocks need room for at least one element"
+        );
+        assert!(size_of::<T>() > 0, "zero-sized types aren't supported");
+        let largest = match strategy {
+            GrowthStrategy::Fixed => block_capacity,
+            GrowthStrategy::Doubling { max } => {
+                assert!(
+                    max >= block_capacity,
+                    "blocks can't grow to {max} elements from {block_capacity}"
+                );
+                max
+            }
+        };
+        Layout::array::<T>(largest).expect("block size overflows isize");
+        DynPool {
+            blocks: UnsafeCell::new(Vec::new()),
+            starts: UnsafeCell::new(Vec::new()),
+            block_capacity,
+            strategy,
+            len: Cell::new(0),
+            marked: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    fn blocks(&self) -> &[BlockMemory] {
+        unsafe { &*self.blocks.get() }
+    }
+
+    /// Index of the first element of every block.
+    fn starts(&self) -> &[usize] {
+        unsafe { &*self.starts.get() }
+    }
+
+    fn block_layout(&self, block: usize) -> Layout {
+        Layout::array::<T>(self.block_capacity_of(block)).unwrap()
+    }
+
+    /// Number of elements the first block holds
+    pub fn block_capacity(&self) -> usize {
+        self.block_capacity
+    }
+
+    /// Number of elements the block with the given index holds
+    pub fn block_capacity_of(&self, block: usize) -> usize {
+        self.strategy.block_capacity(self.block_capacity, block)
+    }
+
+    pub fn strategy(&self) -> GrowthStrategy {
+        self.strategy
+    }
+
+    /// Number of blocks held by the pool
+    pub fn block_count(&self) -> usize {
+        self.blocks().len()
+    }
+
+    /// Number of elements pushed since the start of the pool
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of elements the allocated blocks can hold
+    pub fn capacity(&self) -> usize {
+        match self.starts().last() {
+            Some(start) => start + self.block_capacity_of(self.block_count() - 1),
+            None => 0,
+        }
+    }
+
+    /// Slot of the element with the given index.
+    fn slot(&self, index: usize) -> NonNull<T> {
+        let block = self.starts().partition_point(|&start| start <= index) - 1;
+        let begin = self.blocks()[block].as_ptr().cast::<T>();
+        unsafe { begin.add(index - self.starts()[block]) }
+    }
+
+    /// Take the next free slot, allocating a block if every one is full.
+    fn bump(&self) -> NonNull<T> {
+        let index = self.len.get();
+        if index == self.capacity() {
+            let layout = self.block_layout(self.block_count());
+            // Readers look blocks up in the table again on every step, so no
+            // slice of it is alive while a block is being appended.
+            unsafe {
+                (*self.blocks.get()).push(BlockMemory::new(layout));
+                (*self.starts.get()).push(index);
+            }
+        }
+        self.len.set(index + 1);
+        self.slot(index)
+    }
+
+    /// Push a value at the end of the pool
+    pub fn push(&mut self, value: T) {
+        self.alloc(value);
+    }
+
+    /// Move `value` into the pool and return a reference to it, valid until
+    /// the pool is rewound past it. The reference is shared, as the pool's
+    /// other readers can reach the value too.
+    pub fn alloc(&self, value: T) -> &T {
+        let ptr = self.bump();
+        unsafe {
+            ptr.as_ptr().write(value);
+            &*ptr.as_ptr()
+        }
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len()).then(|| unsafe { &*self.slot(index).as_ptr() })
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        (index < self.len()).then(|| unsafe { &mut *self.slot(index).as_ptr() })
+    }
+
+    /// The used part of every block, in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &[T]> {
+        let len = self.len();
+        // `alloc` can grow the table between two steps, so it isn't borrowed across them.
+        (0..self.block_count())
+            .map(move |block| {
+                let used = len
+                    .saturating_sub(self.starts()[block])
+                    .min(self.block_capacity_of(block));
+                let begin = self.blocks()[block].as_ptr().cast::<T>();
+                unsafe { std::slice::from_raw_parts(begin.as_ptr(), used) }
+            })
+            .take_while(|chunk| !chunk.is_empty())
+    }
+
+    /// Iterate over the elements pushed since the start of the pool.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks().flatten()
+    }
+
+    /// Drop the elements from `index` on and move the cursor back to it. The
+    /// cursor never moves forward.
+    fn rewind_to(&mut self, index: usize) {
+        let end = self.len();
+        let index = index.min(end);
+        self.len.set(index);
+        if needs_drop::<T>() {
+            for index in index..end {
+                unsafe { self.slot(index).as_ptr().drop_in_place() };
+            }
+        }
+    }
+
+    /// Rewind to the first block
+    pub fn rewind(&mut self) {
+        self.rewind_to(0);
+    }
+
+    /// Mark the current position
+    pub fn mark(&mut self) {
+        self.marked = self.len();
+    }
+
+    /// Rewind to the marked position
+    pub fn rewind_to_mark(&mut self) {
+        self.rewind_to(self.marked);
+    }
+
+    /// Check the pool's internal invariants. A broken one is a violation
+    /// under the crate's `Policy`.
+    pub fn validate(&self) -> Result<(), Violation> {
+        ensure!(
+            self.len() <= self.capacity(),
+            Violation::BrokenInvariant,
+            "the cursor is past the last block"
+        );
+        ensure!(
+            self.blocks().len() == self.starts().len(),
+            Violation::BrokenInvariant,
+            "blocks and starts are out of sync"
+        );
+        let mut start = 0;
+        for (index, block) in self.blocks().iter().enumerate() {
+            ensure!(
+                block.layout() == self.block_layout(index),
+                Violation::BrokenInvariant,
+                "block {index} has the wrong layout"
+            );
+            ensure!(
+                self.starts()[index] == start,
+                Violation::BrokenInvariant,
+                "block {index} starts at the wrong element"
+            );
+            start += self.block_capacity_of(index);
+        }
+        Ok(())
+    }
+}
+
+impl<T> BlockPool for DynPool<T> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        DynPool::push(self, value)
+    }
+
+    fn mark(&mut self) {
+        DynPool::mark(self)
+    }
+
+    fn rewind(&mut self) {
+        DynPool::rewind(self)
+    }
+
+    fn rewind_to_mark(&mut self) {
+        DynPool::rewind_to_mark(self)
+    }
+
+    fn block_count(&self) -> usize {
+        DynPool::block_count(self)
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        DynPool::validate(self)
+    }
+}
+
+impl<T> Extend<T> for DynPool<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| self.push(value));
+    }
+}
+
+impl<T> Drop for DynPool<T> {
+    fn drop(&mut self) {
+        self.rewind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockpool::CheckedPool;
+
+    #[test]
+    fn test_dyn_pool() {
+        let block_capacity = std::hint::black_box(3);
+        let mut pool = DynPool::<u32>::new(block_capacity);
+        assert_eq!(pool.block_count(), 0);
+        pool.extend(0..7);
+        assert_eq!(pool.block_count(), 3);
+        assert_eq!(
+            pool.chunks().map(<[u32]>::len).collect::<Vec<_>>(),
+            [3, 3, 1]
+        );
+        assert_eq!(pool.get(4), Some(&4));
+        assert_eq!(pool.get(7), None);
+        assert_eq!(*pool.alloc(17), 17);
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 6, 17]
+        );
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    fn test_dyn_pool_alloc_while_iterating_chunks() {
+        let pool = DynPool::<u64>::new(2);
+        for i in 0..6 {
+            pool.alloc(i);
+        }
+        let mut seen = Vec::new();
+        for chunk in pool.chunks() {
+            for i in 0..8 {
+                pool.alloc(100 + i);
+            }
+            seen.extend_from_slice(chunk);
+        }
+        assert_eq!(seen, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(pool.len(), 30);
+    }
+
+    #[test]
+    fn test_dyn_pool_doubling() {
+        let mut pool = DynPool::with_strategy(2, GrowthStrategy::Doubling { max: 8 });
+        pool.extend(0..30);
+        assert_eq!(
+            pool.chunks().map(<[u32]>::len).collect::<Vec<_>>(),
+            [2, 4, 8, 8, 8]
+        );
+        assert_eq!(pool.capacity(), 30);
+        assert_eq!(pool.get(6), Some(&6));
+        assert_eq!(pool.get(29), Some(&29));
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            (0..30).collect::<Vec<_>>()
+        );
+        pool.rewind();
+        pool.extend(0..3);
+        assert_eq!(pool.block_count(), 5);
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    fn test_dyn_pool_rewind_to_mark() {
+        let value = std::rc::Rc::new(());
+        let mut pool = DynPool::new(4);
+        for _ in 0..3 {
+            pool.push(value.clone());
+        }
+        pool.mark();
+        for _ in 0..6 {
+            pool.push(value.clone());
+        }
+        pool.rewind_to_mark();
+        assert_eq!(std::rc::Rc::strong_count(&value), 4);
+        assert_eq!((pool.len(), pool.block_count()), (3, 3));
+        pool.rewind();
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+        pool.push(value.clone());
+        drop(pool);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_dyn_pool_as_block_pool() {
+        let mut pool = CheckedPool::new(DynPool::new(5));
+        pool.mark();
+        (0..12).for_each(|i| pool.push(i));
+        pool.rewind_to_mark();
+        pool.push(1);
+        assert_eq!(pool.block_count(), 3);
+        assert_eq!(pool.inner().iter().copied().collect::<Vec<_>>(), [1]);
+    }
+}
@@ -0,0 +1,52 @@
+//! Deterministic allocation failures for tests: arm a failure at the N-th
+//! block allocation made by the current thread, so that fallible paths like
+//! `SmallObjectPool::try_push` can be exercised without running out of memory.
+
+use std::cell::Cell;
+
+thread_local! {
+    static COUNTDOWN: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Make the `n`-th block allocation from now on (1-based) on this thread fail.
+pub fn fail_at(n: usize) {
+    assert!(n > 0, "allocations are counted from 1");
+    COUNTDOWN.with(|countdown| countdown.set(Some(n)));
+}
+
+/// Cancel a failure armed with `fail_at` that hasn't triggered yet.
+pub fn disarm() {
+    COUNTDOWN.with(|countdown| countdown.set(None));
+}
+
+/// Count a block allocation, returning whether it should fail.
+pub(crate) fn should_fail() -> bool {
+    COUNTDOWN.with(|countdown| match countdown.get() {
+        Some(1) => {
+            countdown.set(None);
+            true
+        }
+        Some(n) => {
+            countdown.set(Some(n - 1));
+            false
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fail_at() {
+        fail_at(3);
+        assert!(!should_fail());
+        assert!(!should_fail());
+        assert!(should_fail());
+        assert!(!should_fail());
+        fail_at(1);
+        disarm();
+        assert!(!should_fail());
+    }
+}
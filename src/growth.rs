@@ -0,0 +1,167 @@
+use crate::smallobjectpool::SmallObjectPool;
+
+/// # Growth
+/// How many blocks a pool may hold, chosen in its type so that code which
+/// must never allocate can require a `Fixed` pool.
+pub trait Growth {
+    /// Most blocks the pool may hold, or `None` if it grows without bound.
+    const MAX_BLOCKS: Option<usize>;
+}
+
+/// # Growable
+/// Pools that allocate a new block whenever they run out of room.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Growable;
+
+impl Growth for Growable {
+    const MAX_BLOCKS: Option<usize> = None;
+}
+
+/// # Fixed
+/// Pools that allocate `MAX_BLOCKS` blocks when created and never again.
+/// Once they are full, `push` panics and `try_push` hands the value back.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fixed<const MAX_BLOCKS: usize>;
+
+impl<const MAX_BLOCKS: usize> Growth for Fixed<MAX_BLOCKS> {
+    const MAX_BLOCKS: Option<usize> = Some(MAX_BLOCKS);
+}
+
+/// # GrowthStrategy
+/// How the size of the blocks of a `DynPool` changes as it grows, for
+/// workloads whose size isn't known up front: small blocks waste little
+/// memory on small workloads, large ones keep iteration over large workloads
+/// from fragmenting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrowthStrategy {
+    /// Every block holds the pool's block capacity.
+    #[default]
+    Fixed,
+    /// Every block holds twice as many elements as the one before it, up
+    /// to `max` elements.
+    Doubling { max: usize },
+}
+
+impl GrowthStrategy {
+    /// Number of elements the block with the given index holds, when the
+    /// first one holds `first`.
+    pub fn block_capacity(self, first: usize, index: usize) -> usize {
+        match self {
+            GrowthStrategy::Fixed => first,
+            GrowthStrategy::Doubling { max } => {
+                let factor = u32::try_from(index)
+                    .ok()
+                    .and_then(|index| 1usize.checked_shl(index))
+                    .unwrap_or(usize::MAX);
+                first.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+/// A pool of at most `MAX_BLOCKS` blocks of `CAP` elements, all allocated up front.
+pub type FixedPool<T, const CAP: usize, const MAX_BLOCKS: usize> =
+    SmallObjectPool<T, CAP, (), Fixed<MAX_BLOCKS>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocksource::{BlockRecycler, BlockSource},
+        smallobjectpool::PoolFull,
+    };
+
+    /// Stands in for code that must never allocate, e.g. an audio callback.
+    fn fill(pool: &mut FixedPool<u32, 4, 2>, from: u32) -> Result<(), PoolFull<u32>> {
+        (from..from + 5).try_for_each(|i| pool.try_push(i))
+    }
+
+    #[test]
+    fn test_growth_fixed_pool() {
+        let mut pool = FixedPool::<u32, 4, 2>::new();
+        assert_eq!(pool.block_count(), 2);
+        assert_eq!(fill(&mut pool, 0), Ok(()));
+        assert_eq!(fill(&mut pool, 5), Err(PoolFull(8)));
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.block_count(), 2);
+        pool.rewind();
+        assert_eq!(fill(&mut pool, 0), Ok(()));
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the pool is full")]
+    fn test_growth_fixed_pool_push_when_full() {
+        let _policy = crate::policy::default_policy();
+        let mut pool = FixedPool::<u32, 4, 1>::new();
+        pool.extend(0..5);
+    }
+
+    #[test]
+    fn test_growth_strategy_block_capacity() {
+        let doubling = GrowthStrategy::Doubling { max: 100 };
+        assert_eq!(
+            (0..5)
+                .map(|i| doubling.block_capacity(8, i))
+                .collect::<Vec<_>>(),
+            [8, 16, 32, 64, 100]
+        );
+        assert_eq!(doubling.block_capacity(8, 200), 100);
+        assert_eq!(GrowthStrategy::Fixed.block_capacity(8, 3), 8);
+    }
+
+    #[test]
+    fn test_growth_bounded_pool() {
+        let max_blocks = std::hint::black_box(2);
+        let mut pool = SmallObjectPool::<u32, 4>::bounded(max_blocks);
+        assert_eq!((pool.block_count(), pool.max_blocks()), (2, Some(2)));
+        assert_eq!((0..8).try_for_each(|i| pool.try_push(i)), Ok(()));
+        assert_eq!(pool.try_push(8), Err(PoolFull(8)));
+        assert_eq!(pool.block_count(), 2);
+        pool.validate().unwrap();
+        let mut pool = pool.into_growable();
+        assert_eq!(pool.max_blocks(), None);
+        pool.push(8);
+        assert_eq!(pool.block_count(), 3);
+    }
+
+    #[test]
+    fn test_growth_fixed_pool_keeps_blocks() {
+        let mut recycler = BlockRecycler::for_pool::<u32, 4>();
+        let mut pool = FixedPool::<u32, 4, 3>::new();
+        pool.push(0);
+        assert_eq!(pool.donate_free_blocks(&mut recycler), 0);
+        assert_eq!((pool.block_count(), recycler.len()), (3, 0));
+
+        let mut pool = SmallObjectPool::<u32, 4>::bounded(3);
+        pool.push(0);
+        assert_eq!(pool.shrink_to_fit(), 0);
+        assert_eq!(pool.donate_free_blocks(&mut recycler), 0);
+        assert_eq!(pool.block_count(), 3);
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    fn test_growth_conversions() {
+        let mut pool = SmallObjectPool::<u32, 4>::new();
+        pool.extend(0..10);
+        let Err(pool) = pool.into_fixed::<2>() else {
+            panic!("10 elements don't fit in 2 blocks of 4");
+        };
+        let Ok(mut pool) = pool.into_fixed::<4>() else {
+            panic!("10 elements fit in 4 blocks of 4");
+        };
+        assert_eq!(pool.block_count(), 4);
+        pool.extend(10..16);
+        assert!(pool.try_push(16).is_err());
+        let mut pool = pool.into_growable();
+        pool.push(16);
+        assert_eq!(pool.block_count(), 5);
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            (0..17).collect::<Vec<_>>()
+        );
+        pool.validate().unwrap();
+    }
+}
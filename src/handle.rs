@@ -0,0 +1,93 @@
+use std::marker::PhantomData;
+
+use crate::index::{BlockIndex, SlotIndex};
+
+/// # PoolHandle
+/// Refers to an element pushed with `SmallObjectPool::push_handle`. Unlike a
+/// pointer, a handle is checked against the pool it is resolved with: it
+/// resolves to nothing once the pool is rewound or pruned past its element,
+/// or when given to a different pool. The handle records the generation of
+/// its slot, so it keeps resolving to nothing after the slot is written
+/// again, and the serial number of its block, in case the block is freed
+/// and another one takes its place.
+pub struct PoolHandle<T> {
+    pub(crate) pool_id: usize,
+    pub(crate) index: BlockIndex,
+    pub(crate) slot: SlotIndex,
+    pub(crate) serial: u64,
+    pub(crate) generation: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PoolHandle<T> {
+    pub(crate) fn new(
+        pool_id: usize,
+        index: BlockIndex,
+        slot: SlotIndex,
+        serial: u64,
+        generation: u64,
+    ) -> Self {
+        PoolHandle {
+            pool_id,
+            index,
+            slot,
+            serial,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Identifier of the pool the element was pushed to
+    pub fn pool_id(&self) -> usize {
+        self.pool_id
+    }
+
+    /// Index of the block the element is in
+    pub fn block_index(&self) -> BlockIndex {
+        self.index
+    }
+
+    /// Index of the element's slot within its block
+    pub fn slot(&self) -> SlotIndex {
+        self.slot
+    }
+}
+
+impl<T> Clone for PoolHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PoolHandle<T> {}
+
+impl<T> PartialEq for PoolHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (
+            self.pool_id,
+            self.index,
+            self.slot,
+            self.serial,
+            self.generation,
+        ) == (
+            other.pool_id,
+            other.index,
+            other.slot,
+            other.serial,
+            other.generation,
+        )
+    }
+}
+
+impl<T> Eq for PoolHandle<T> {}
+
+impl<T> std::fmt::Debug for PoolHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolHandle")
+            .field("pool_id", &self.pool_id)
+            .field("index", &self.index)
+            .field("slot", &self.slot)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
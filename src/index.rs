@@ -0,0 +1,113 @@
+use std::fmt;
+
+/// Integer type used for block indices. Defaults to `usize`; the `index-u32`
+/// and `index-u16` features shrink it (and every position or handle that
+/// stores one) for memory-constrained targets.
+#[cfg(feature = "index-u16")]
+pub type BlockIndex = u16;
+#[cfg(all(feature = "index-u32", not(feature = "index-u16")))]
+pub type BlockIndex = u32;
+#[cfg(not(any(feature = "index-u16", feature = "index-u32")))]
+pub type BlockIndex = usize;
+
+/// Integer type used for slot indices within a block. Defaults to `usize`;
+/// the `slot-u32` and `slot-u16` features shrink it independently of
+/// `BlockIndex`, since a block's `CAP` is usually far smaller than the number
+/// of blocks. Pools whose `CAP` doesn't fit fail to compile.
+#[cfg(feature = "slot-u16")]
+pub type SlotIndex = u16;
+#[cfg(all(feature = "slot-u32", not(feature = "slot-u16")))]
+pub type SlotIndex = u32;
+#[cfg(not(any(feature = "slot-u16", feature = "slot-u32")))]
+pub type SlotIndex = usize;
+
+/// # IndexOverflow
+/// A value that doesn't fit in the configured index type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexOverflow {
+    pub value: usize,
+    pub max: usize,
+}
+
+impl fmt::Display for IndexOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "index {} exceeds the maximum of {} for the configured index type",
+            self.value, self.max
+        )
+    }
+}
+
+impl std::error::Error for IndexOverflow {}
+
+/// Convert `value` to a `BlockIndex`, failing if it doesn't fit.
+pub fn block_index(value: usize) -> Result<BlockIndex, IndexOverflow> {
+    BlockIndex::try_from(value).map_err(|_| IndexOverflow {
+        value,
+        max: to_usize(BlockIndex::MAX),
+    })
+}
+
+/// Convert `value` to a `BlockIndex`, clamping it to the maximum.
+pub fn block_index_saturating(value: usize) -> BlockIndex {
+    block_index(value).unwrap_or(BlockIndex::MAX)
+}
+
+/// Convert `value` to a `SlotIndex`, failing if it doesn't fit.
+pub fn slot_index(value: usize) -> Result<SlotIndex, IndexOverflow> {
+    SlotIndex::try_from(value).map_err(|_| IndexOverflow {
+        value,
+        max: slot_to_usize(SlotIndex::MAX),
+    })
+}
+
+/// Convert `value` to a `SlotIndex`, clamping it to the maximum.
+pub fn slot_index_saturating(value: usize) -> SlotIndex {
+    slot_index(value).unwrap_or(SlotIndex::MAX)
+}
+
+/// Widen an index back to `usize`; lossless for every configured index type.
+#[allow(clippy::unnecessary_cast)]
+pub const fn to_usize(index: BlockIndex) -> usize {
+    index as usize
+}
+
+/// Widen a slot index back to `usize`; lossless for every configured slot type.
+#[allow(clippy::unnecessary_cast)]
+pub const fn slot_to_usize(slot: SlotIndex) -> usize {
+    slot as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_conversions() {
+        assert_eq!(block_index(7), Ok(7));
+        assert_eq!(slot_to_usize(slot_index(3).unwrap()), 3);
+        let max = to_usize(BlockIndex::MAX);
+        if max < usize::MAX {
+            assert_eq!(
+                block_index(max + 1),
+                Err(IndexOverflow {
+                    value: max + 1,
+                    max
+                })
+            );
+            assert_eq!(block_index_saturating(max + 1), BlockIndex::MAX);
+        }
+        assert_eq!(slot_index_saturating(usize::MAX), SlotIndex::MAX);
+        let max = slot_to_usize(SlotIndex::MAX);
+        if max < usize::MAX {
+            assert_eq!(
+                slot_index(max + 1),
+                Err(IndexOverflow {
+                    value: max + 1,
+                    max
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    mem::{needs_drop, MaybeUninit},
+    ops::{Index, IndexMut},
+};
+
+use crate::{
+    blockpool::BlockPool,
+    policy::{ensure, Violation},
+    smallobjectpool::SmallObjectPool,
+};
+
+/// # InlinePool
+/// A pool whose first block of `CAP` elements is stored in the pool itself,
+/// in the manner of `smallvec`, with the blocks after it in a
+/// `SmallObjectPool` that only allocates once the inline block is full. A
+/// short-lived pool that never outgrows its first block makes no heap
+/// allocation at all. Elements don't move when the pool spills, but those in
+/// the inline block move with the pool, so they are only reachable by index
+/// or through a borrow of the pool.
+pub struct InlinePool<T, const CAP: usize> {
+    first: [MaybeUninit<T>; CAP],
+    /// Number of elements in `first`; it is full whenever `rest` holds any.
+    len: usize,
+    marked: usize,
+    rest: SmallObjectPool<T, CAP>,
+}
+
+impl<T, const CAP: usize> InlinePool<T, CAP> {
+    pub fn new() -> Self {
+        InlinePool {
+            first: [const { MaybeUninit::uninit() }; CAP],
+            len: 0,
+            marked: 0,
+            rest: SmallObjectPool::new(),
+        }
+    }
+
+    /// Number of blocks held by the pool, counting the inline one.
+    pub fn block_count(&self) -> usize {
+        1 + self.rest.block_count()
+    }
+
+    /// Whether the pool has outgrown its inline block and allocated blocks
+    /// on the heap.
+    pub fn spilled(&self) -> bool {
+        self.rest.block_count() > 0
+    }
+
+    /// Number of elements pushed since the start of the pool
+    pub fn len(&self) -> usize {
+        self.len + self.rest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the pool can hold before allocating another block
+    pub fn capacity(&self) -> usize {
+        CAP + self.rest.capacity()
+    }
+
+    /// Push a value at the end of the pool, in the inline block while it has room.
+    pub fn push(&mut self, value: T) {
+        if self.len < CAP {
+            self.first[self.len].write(value);
+            self.len += 1;
+        } else {
+            self.rest.push(value);
+        }
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match index.checked_sub(CAP) {
+            None => (index < self.len).then(|| unsafe { self.first[index].assume_init_ref() }),
+            Some(index) => self.rest.get(index),
+        }
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        match index.checked_sub(CAP) {
+            None => (index < self.len).then(|| unsafe { self.first[index].assume_init_mut() }),
+            Some(index) => self.rest.get_mut(index),
+        }
+    }
+
+    /// Iterate over the elements pushed since the start of the pool.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.first[..self.len]
+            .iter()
+            .map(|slot| unsafe { slot.assume_init_ref() })
+            .chain(self.rest.iter())
+    }
+
+    /// Iterate mutably over the elements pushed since the start of the pool.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> {
+        self.first[..self.len]
+            .iter_mut()
+            .map(|slot| unsafe { slot.assume_init_mut() })
+            .chain(self.rest.iter_mut())
+    }
+
+    /// Drop the elements of the inline block from `index` on. The blocks
+    /// after it must have been rewound already.
+    fn truncate_first(&mut self, index: usize) {
+        let end = self.len;
+        self.len = index.min(end);
+        if needs_drop::<T>() {
+            for slot in &mut self.first[self.len..end] {
+                unsafe { slot.assume_init_drop() };
+            }
+        }
+    }
+
+    /// Rewind to the first block, keeping the heap blocks for reuse.
+    pub fn rewind(&mut self) {
+        self.rest.rewind();
+        self.truncate_first(0);
+    }
+
+    /// Mark the current position
+    pub fn mark(&mut self) {
+        self.marked = self.len;
+        self.rest.mark();
+    }
+
+    /// Rewind to the marked position. A mark in the inline block was taken
+    /// while the heap blocks were empty, so they are emptied again.
+    pub fn rewind_to_mark(&mut self) {
+        self.rest.rewind_to_mark();
+        self.truncate_first(self.marked);
+    }
+
+    /// Check the pool's internal invariants. A broken one is a violation
+    /// under the crate's `Policy`.
+    pub fn validate(&self) -> Result<(), Violation> {
+        ensure!(
+            self.len <= CAP,
+            Violation::BrokenInvariant,
+            "the inline block is overfilled"
+        );
+        ensure!(
+            self.len == CAP || self.rest.is_empty(),
+            Violation::BrokenInvariant,
+            "the heap blocks hold elements before the inline block is full"
+        );
+        self.rest.validate()
+    }
+}
+
+impl<T, const CAP: usize> BlockPool for InlinePool<T, CAP> {
+    type Item = T;
+
+    fn push(&mut self, value: T) {
+        InlinePool::push(self, value)
+    }
+
+    fn mark(&mut self) {
+        InlinePool::mark(self)
+    }
+
+    fn rewind(&mut self) {
+        InlinePool::rewind(self)
+    }
+
+    fn rewind_to_mark(&mut self) {
+        InlinePool::rewind_to_mark(self)
+    }
+
+    fn block_count(&self) -> usize {
+        InlinePool::block_count(self)
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        InlinePool::validate(self)
+    }
+}
+
+impl<T, const CAP: usize> Default for InlinePool<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize> Index<usize> for InlinePool<T, CAP> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let len = self.len();
+        self.get(index)
+            .unwrap_or_else(|| panic!("index {index} out of bounds for a pool of {len} elements"))
+    }
+}
+
+impl<T, const CAP: usize> IndexMut<usize> for InlinePool<T, CAP> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len();
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("index {index} out of bounds for a pool of {len} elements"))
+    }
+}
+
+impl<T, const CAP: usize> Drop for InlinePool<T, CAP> {
+    fn drop(&mut self) {
+        // The heap blocks drop their own elements.
+        self.truncate_first(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_pool_spills() {
+        let mut pool = InlinePool::<u32, 4>::new();
+        for i in 0..4 {
+            pool.push(i);
+        }
+        assert!(!pool.spilled());
+        assert_eq!((pool.len(), pool.block_count()), (4, 1));
+        pool.mark();
+        for i in 4..10 {
+            pool.push(i);
+        }
+        assert!(pool.spilled());
+        assert_eq!((pool.len(), pool.block_count()), (10, 3));
+        assert_eq!(
+            (pool.get(3), pool.get(4), pool.get(10)),
+            (Some(&3), Some(&4), None)
+        );
+        pool[9] += 1;
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 10]
+        );
+        assert_eq!(pool.iter().next_back(), Some(&10));
+        pool.validate().unwrap();
+        pool.rewind_to_mark();
+        assert_eq!(pool.len(), 4);
+        pool.rewind();
+        assert!(pool.is_empty());
+        // The heap blocks are kept for the next spill.
+        (0..10).for_each(|i| pool.push(i));
+        assert_eq!(pool.block_count(), 3);
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    fn test_inline_pool_mark_in_inline_block() {
+        let mut pool = InlinePool::<String, 2>::new();
+        pool.push(String::from("a"));
+        pool.mark();
+        for value in ["b", "c", "d"] {
+            pool.push(String::from(value));
+        }
+        pool.iter_mut().for_each(|value| value.push('!'));
+        assert_eq!(pool[3], "d!");
+        pool.rewind_to_mark();
+        assert_eq!(pool.iter().collect::<Vec<_>>(), ["a!"]);
+        pool.validate().unwrap();
+    }
+
+    #[test]
+    fn test_inline_pool_drops() {
+        let value = std::rc::Rc::new(());
+        let mut pool = InlinePool::<_, 2>::new();
+        for _ in 0..5 {
+            pool.push(value.clone());
+        }
+        pool.rewind_to_mark();
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+        for _ in 0..3 {
+            pool.push(value.clone());
+        }
+        drop(pool);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+}
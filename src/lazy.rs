@@ -0,0 +1,165 @@
+use std::{
+    cell::{Cell, OnceCell},
+    fmt,
+    ops::Deref,
+};
+
+use crate::rawpool::RawPool;
+
+/// A thunk and the value it computes, stored side by side in the pool.
+struct Thunk<T, F> {
+    value: OnceCell<T>,
+    thunk: Cell<Option<F>>,
+}
+
+/// Evaluation of a `Thunk` whatever its closure type.
+trait Force<T> {
+    fn force(&self) -> &T;
+    fn get(&self) -> Option<&T>;
+}
+
+impl<T, F: FnOnce() -> T> Force<T> for Thunk<T, F> {
+    fn force(&self) -> &T {
+        self.value.get_or_init(|| match self.thunk.take() {
+            Some(thunk) => thunk(),
+            None => panic!("a lazy value depends on itself"),
+        })
+    }
+
+    fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+}
+
+/// # Lazy
+/// A value computed on first access by a closure, with both the closure and
+/// the value stored in a `RawPool`. A `Lazy` is `Copy`, so closures can
+/// capture the ones they depend on and whole evaluation graphs live in the
+/// arena, with only the nodes that are asked for ever being computed.
+/// Like everything in a `RawPool`, the closures and values are never dropped,
+/// so they shouldn't own other resources.
+pub struct Lazy<'pool, T> {
+    thunk: &'pool (dyn Force<T> + 'pool),
+}
+
+impl<'pool, T: 'pool> Lazy<'pool, T> {
+    /// Store `f` in `pool`, to be called the first time the value is needed.
+    ///
+    /// # Panics
+    /// If the closure and the value don't fit in a block of the pool.
+    pub fn new_in<const CAP: usize, F>(pool: &'pool RawPool<CAP>, f: F) -> Self
+    where
+        F: FnOnce() -> T + 'pool,
+    {
+        let thunk = pool.alloc_value(Thunk {
+            value: OnceCell::new(),
+            thunk: Cell::new(Some(f)),
+        });
+        // The pool can't be rewound while it is borrowed, so the thunk stays put.
+        Lazy {
+            thunk: unsafe { thunk.as_ref() },
+        }
+    }
+
+    /// Store an already computed value in `pool`, e.g. an input of the graph.
+    pub fn ready_in<const CAP: usize>(pool: &'pool RawPool<CAP>, value: T) -> Self {
+        let thunk = pool.alloc_value(Thunk::<T, fn() -> T> {
+            value: OnceCell::from(value),
+            thunk: Cell::new(None),
+        });
+        Lazy {
+            thunk: unsafe { thunk.as_ref() },
+        }
+    }
+}
+
+impl<'pool, T> Lazy<'pool, T> {
+    /// The value, computing it first if this is the first access.
+    ///
+    /// # Panics
+    /// If computing the value needs the value itself.
+    pub fn force(&self) -> &'pool T {
+        self.thunk.force()
+    }
+
+    /// The value, if it has been computed.
+    pub fn get(&self) -> Option<&'pool T> {
+        self.thunk.get()
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        self.get().is_some()
+    }
+}
+
+impl<T> Clone for Lazy<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Lazy<'_, T> {}
+
+impl<T> Deref for Lazy<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.force()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<unevaluated>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_graph() {
+        let evaluations = Cell::new(0);
+        let evaluate = |value: f64| {
+            evaluations.set(evaluations.get() + 1);
+            value
+        };
+        let pool = RawPool::<256>::new();
+        let spot = Lazy::ready_in(&pool, 100.0_f64);
+        let strike = Lazy::ready_in(&pool, 90.0);
+        let intrinsic = Lazy::new_in(&pool, || evaluate((*spot - *strike).max(0.0)));
+        let discounted = Lazy::new_in(&pool, || evaluate(*intrinsic * 0.5));
+        let unused = Lazy::new_in(&pool, || evaluate(*spot * 2.0));
+
+        assert_eq!(format!("{discounted:?}"), "Lazy(<unevaluated>)");
+        assert_eq!(*discounted.force(), 5.0);
+        assert_eq!(*discounted, 5.0);
+        assert_eq!(evaluations.get(), 2);
+        assert!(intrinsic.is_evaluated() && !unused.is_evaluated());
+        assert_eq!(format!("{intrinsic:?}"), "Lazy(10.0)");
+    }
+
+    #[test]
+    fn test_lazy_mixed_types() {
+        let pool = RawPool::<256>::new();
+        let count = Lazy::ready_in(&pool, 3u32);
+        let area = Lazy::new_in(&pool, || f64::from(*count) * 1.5);
+        let above = Lazy::new_in(&pool, || *area > 4.0);
+        assert!(*above);
+        assert_eq!(area.get(), Some(&4.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "a lazy value depends on itself")]
+    fn test_lazy_cycle() {
+        let pool = RawPool::<256>::new();
+        let slot = Cell::new(None::<Lazy<u32>>);
+        let value = Lazy::new_in(&pool, || *slot.get().unwrap().force() + 1);
+        slot.set(Some(value));
+        value.force();
+    }
+}
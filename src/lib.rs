@@ -1,5 +1,44 @@
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(feature = "allocator-api")]
+#[cfg_attr(docsrs, doc(cfg(feature = "allocator-api")))]
+pub mod allocator;
 pub mod arraylike;
+pub mod blockmeta;
+pub mod blockpool;
+pub mod blocksource;
+pub mod builder;
+pub mod concurrentpool;
+pub mod dynpool;
+#[cfg(any(test, feature = "failing-alloc"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "failing-alloc")))]
+pub mod failing_alloc;
+pub mod growth;
+pub mod handle;
+pub mod index;
+pub mod inlinepool;
+pub mod lazy;
+#[cfg(feature = "track-lifetimes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "track-lifetimes")))]
+pub mod lifetimes;
 pub mod linkedlist;
+pub mod node;
+pub mod number;
+pub mod policy;
+pub mod poolrc;
+pub mod poolvec;
 pub mod prelude;
 pub mod ptrbased;
+#[cfg(any(test, feature = "raw"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "raw")))]
+pub mod raw;
+pub mod rawpool;
+pub mod relocate;
+pub mod shardedpool;
+pub mod slotmap;
 pub mod smallobjectpool;
+pub mod staticpool;
+pub mod syncpool;
+pub mod tail;
+pub mod tape;
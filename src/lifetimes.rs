@@ -0,0 +1,113 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::index::BlockIndex;
+
+/// # LifetimeTracker
+/// Records when each block of a pool starts being written and when its
+/// elements are rewound or discarded. A block's elements are timed together
+/// as one segment, from the time the cursor enters the block to the time
+/// they are released.
+#[derive(Default)]
+pub(crate) struct LifetimeTracker {
+    started: RefCell<BTreeMap<BlockIndex, Instant>>,
+    lifetimes: RefCell<Vec<Duration>>,
+}
+
+impl LifetimeTracker {
+    /// Start the segment of the block with the given index, unless it has one.
+    pub(crate) fn entered(&self, index: BlockIndex) {
+        self.started
+            .borrow_mut()
+            .entry(index)
+            .or_insert_with(Instant::now);
+    }
+
+    /// End the segments of the blocks with indices in `from..=to`.
+    pub(crate) fn released(&self, from: BlockIndex, to: BlockIndex) {
+        let now = Instant::now();
+        let mut started = self.started.borrow_mut();
+        let ended: Vec<BlockIndex> = started.range(from..=to).map(|(&index, _)| index).collect();
+        let mut lifetimes = self.lifetimes.borrow_mut();
+        for index in ended {
+            let start = started.remove(&index).unwrap();
+            lifetimes.push(now - start);
+        }
+    }
+
+    pub(crate) fn report(&self) -> LifetimeReport {
+        let mut lifetimes = self.lifetimes.borrow().clone();
+        lifetimes.sort_unstable();
+        let percentile = |p: usize| {
+            lifetimes
+                .len()
+                .checked_sub(1)
+                .map_or(Duration::ZERO, |last| lifetimes[last * p / 100])
+        };
+        LifetimeReport {
+            segments: lifetimes.len(),
+            live_segments: self.started.borrow().len(),
+            mean: match lifetimes.len() {
+                0 => Duration::ZERO,
+                count => lifetimes.iter().sum::<Duration>() / count as u32,
+            },
+            min: percentile(0),
+            median: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: percentile(100),
+        }
+    }
+}
+
+/// # LifetimeReport
+/// Distribution of how long the pool's segments lived between being written
+/// and being rewound or discarded. Short, uniform lifetimes suit a frame
+/// allocator; lifetimes ending in push order suit a ring; long, scattered
+/// ones a free list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LifetimeReport {
+    /// Number of segments released so far
+    pub segments: usize,
+    /// Number of segments still holding elements
+    pub live_segments: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::smallobjectpool::SmallObjectPool;
+
+    #[test]
+    fn test_lifetimes_report() {
+        let mut pool = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(pool.lifetime_report().segments, 0);
+        for _ in 0..3 {
+            pool.extend(0..6);
+            pool.rewind();
+        }
+        let report = pool.lifetime_report();
+        assert_eq!(report.segments, 6);
+        assert_eq!(report.live_segments, 1);
+
+        pool.extend(0..10);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let position = pool.position();
+        pool.push(10);
+        pool.discard_before(position);
+        let report = pool.lifetime_report();
+        assert_eq!(report.segments, 8);
+        assert_eq!(report.live_segments, 1);
+        assert!(report.max >= std::time::Duration::from_millis(20));
+        assert!(report.min <= report.median && report.median <= report.p90);
+        assert!(report.p90 <= report.p99 && report.p99 <= report.max);
+    }
+}
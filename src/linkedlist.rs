@@ -1,4 +1,7 @@
-use std::ptr::NonNull;
+use std::{
+    alloc::{alloc, handle_alloc_error, Layout},
+    ptr::NonNull,
+};
 
 use crate::ptrbased::PtrBased;
 
@@ -37,6 +40,10 @@ pub struct LinkedList<T> {
     end: Option<NonNull<Node<T>>>,
 }
 
+// The list owns its nodes, as the standard library's does.
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
 impl<T> PtrBased for LinkedList<T> {
     type Item = Node<T>;
 
@@ -49,29 +56,17 @@ impl<T> PtrBased for LinkedList<T> {
     }
 
     fn next(&self, ptr: NonNull<Self::Item>) -> Option<NonNull<Self::Item>> {
-        if ptr >= self.end.unwrap() {
-            None
-        } else {
-            unsafe {
-                match ptr.as_ref().next {
-                    Some(next) => Some(next),
-                    None => None,
-                }
-            }
-        }
+        unsafe { ptr.as_ref().next }
     }
 
     fn prev(&self, ptr: NonNull<Self::Item>) -> Option<NonNull<Self::Item>> {
-        if ptr <= self.end.unwrap() {
-            None
-        } else {
-            unsafe {
-                match ptr.as_ref().prev {
-                    Some(prev) => Some(prev),
-                    None => None,
-                }
-            }
-        }
+        unsafe { ptr.as_ref().prev }
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -83,9 +78,31 @@ impl<T> LinkedList<T> {
         }
     }
 
+    /// Allocate a node for `data`, handing `data` back if the allocation fails.
+    fn try_alloc_node(data: T) -> Result<NonNull<Node<T>>, T> {
+        #[cfg(any(test, feature = "failing-alloc"))]
+        if crate::failing_alloc::should_fail() {
+            return Err(data);
+        }
+        let layout = Layout::new::<Node<T>>();
+        match NonNull::new(unsafe { alloc(layout) } as *mut Node<T>) {
+            Some(ptr) => {
+                unsafe { ptr.as_ptr().write(Node::new(data)) };
+                Ok(ptr)
+            }
+            None => Err(data),
+        }
+    }
+
     pub fn push_back(&mut self, data: T) {
-        let new_node = Box::new(Node::new(data));
-        let mut new_node_ptr = NonNull::new(Box::into_raw(new_node)).unwrap();
+        if self.try_push_back(data).is_err() {
+            handle_alloc_error(Layout::new::<Node<T>>());
+        }
+    }
+
+    /// Push to the back of the list, handing `data` back if the node can't be allocated.
+    pub fn try_push_back(&mut self, data: T) -> Result<(), T> {
+        let mut new_node_ptr = Self::try_alloc_node(data)?;
         if let Some(mut end) = self.end {
             unsafe {
                 new_node_ptr.as_mut().prev = Some(end);
@@ -95,6 +112,7 @@ impl<T> LinkedList<T> {
             self.start = Some(new_node_ptr);
         }
         self.end = Some(new_node_ptr);
+        Ok(())
     }
 
     pub fn push_front(&mut self, data: T) {
@@ -112,6 +130,182 @@ impl<T> LinkedList<T> {
             }
         }
     }
+
+    /// Remove the first node and return its data.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let start = self.start?;
+        // Nodes are allocated with the layout `Box` uses, so a box can free them.
+        let node = unsafe { Box::from_raw(start.as_ptr()) };
+        self.start = node.next;
+        match self.start {
+            Some(mut start) => unsafe { start.as_mut().prev = None },
+            None => self.end = None,
+        }
+        Some(node.data)
+    }
+
+    /// Remove the last node and return its data.
+    pub fn pop_back(&mut self) -> Option<T> {
+        let end = self.end?;
+        let node = unsafe { Box::from_raw(end.as_ptr()) };
+        self.end = node.prev;
+        match self.end {
+            Some(mut end) => unsafe { end.as_mut().next = None },
+            None => self.start = None,
+        }
+        Some(node.data)
+    }
+
+    /// Unlink `node` and return its data.
+    ///
+    /// # Safety
+    /// `node` must be a node of this list, e.g. one returned by `begin`,
+    /// `end`, `next` or `prev`, and must not be used afterwards.
+    pub unsafe fn remove(&mut self, node: NonNull<Node<T>>) -> T {
+        let node = unsafe { Box::from_raw(node.as_ptr()) };
+        match node.prev {
+            Some(mut prev) => unsafe { prev.as_mut().next = node.next },
+            None => self.start = node.next,
+        }
+        match node.next {
+            Some(mut next) => unsafe { next.as_mut().prev = node.prev },
+            None => self.end = node.prev,
+        }
+        node.data
+    }
+
+    /// Remove and drop every node.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    /// A cursor on the first node, or on the ghost position if the list is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.start,
+            list: self,
+        }
+    }
+
+    /// A cursor on the last node, or on the ghost position if the list is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.end,
+            list: self,
+        }
+    }
+
+    /// Allocate a node for `data`, aborting if the allocation fails.
+    fn alloc_node(data: T) -> NonNull<Node<T>> {
+        Self::try_alloc_node(data).unwrap_or_else(|_| handle_alloc_error(Layout::new::<Node<T>>()))
+    }
+
+    /// Link a new node for `data` between `prev` and `next`, either of which
+    /// may be the ghost position before the first or after the last node.
+    fn link_between(
+        &mut self,
+        prev: Option<NonNull<Node<T>>>,
+        next: Option<NonNull<Node<T>>>,
+        data: T,
+    ) {
+        let mut node = Self::alloc_node(data);
+        unsafe {
+            node.as_mut().prev = prev;
+            node.as_mut().next = next;
+            match prev {
+                Some(mut prev) => prev.as_mut().next = Some(node),
+                None => self.start = Some(node),
+            }
+            match next {
+                Some(mut next) => next.as_mut().prev = Some(node),
+                None => self.end = Some(node),
+            }
+        }
+    }
+}
+
+/// # CursorMut
+/// A position in a `LinkedList` from which nodes can be inserted, removed or
+/// split off, after std's unstable cursor API. Between the last and the first
+/// node is a ghost position, where `current` is `None`.
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedList<T>,
+    current: Option<NonNull<Node<T>>>,
+}
+
+impl<T> CursorMut<'_, T> {
+    /// Data of the node under the cursor, or `None` on the ghost position.
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.current
+            .map(|mut node| unsafe { &mut node.as_mut().data })
+    }
+
+    /// Move to the next node, from the last node to the ghost position and
+    /// from there to the first node.
+    pub fn move_next(&mut self) {
+        self.current = match self.current {
+            Some(node) => unsafe { node.as_ref().next },
+            None => self.list.start,
+        };
+    }
+
+    /// Move to the previous node, from the first node to the ghost position
+    /// and from there to the last node.
+    pub fn move_prev(&mut self) {
+        self.current = match self.current {
+            Some(node) => unsafe { node.as_ref().prev },
+            None => self.list.end,
+        };
+    }
+
+    /// Insert `data` after the cursor; on the ghost position, at the front.
+    pub fn insert_after(&mut self, data: T) {
+        let next = match self.current {
+            Some(node) => unsafe { node.as_ref().next },
+            None => self.list.start,
+        };
+        self.list.link_between(self.current, next, data);
+    }
+
+    /// Insert `data` before the cursor; on the ghost position, at the back.
+    pub fn insert_before(&mut self, data: T) {
+        let prev = match self.current {
+            Some(node) => unsafe { node.as_ref().prev },
+            None => self.list.end,
+        };
+        self.list.link_between(prev, self.current, data);
+    }
+
+    /// Remove the node under the cursor and return its data, moving the
+    /// cursor to the next node. Returns `None` on the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current?;
+        self.current = unsafe { node.as_ref().next };
+        Some(unsafe { self.list.remove(node) })
+    }
+
+    /// Split the list after the cursor, returning the nodes after it as a
+    /// new list. On the ghost position, the whole list is returned.
+    pub fn split_after(&mut self) -> LinkedList<T> {
+        let Some(mut node) = self.current else {
+            return std::mem::take(self.list);
+        };
+        let Some(mut first) = (unsafe { node.as_mut().next.take() }) else {
+            return LinkedList::new();
+        };
+        unsafe { first.as_mut().prev = None };
+        let end = self.list.end.replace(node);
+        LinkedList {
+            start: Some(first),
+            end,
+        }
+    }
+}
+
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
 }
 
 #[cfg(test)]
@@ -156,4 +350,118 @@ mod tests {
         prev = list.prev(prev).unwrap();
         assert_eq!(unsafe { prev.as_ref().data }, 1);
     }
+
+    #[test]
+    fn test_try_push_back_failure() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        list.push_back(1);
+        crate::failing_alloc::fail_at(2);
+        assert_eq!(list.try_push_back(2), Ok(()));
+        assert_eq!(list.try_push_back(3), Err(3));
+        assert_eq!(unsafe { list.end().unwrap().as_ref().data }, 2);
+    }
+
+    #[test]
+    fn test_pop_front_and_back() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_back(), None);
+        assert!(list.begin().is_none() && list.end().is_none());
+        list.push_back(4);
+        assert_eq!(list.pop_back(), Some(4));
+    }
+
+    #[test]
+    fn test_clear_and_drop_free_nodes() {
+        let value = std::rc::Rc::new(());
+        let mut list = LinkedList::new();
+        for _ in 0..3 {
+            list.push_back(value.clone());
+        }
+        list.clear();
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+        list.push_back(value.clone());
+        list.push_front(value.clone());
+        drop(list);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    fn collect(list: &LinkedList<i32>) -> Vec<i32> {
+        let mut values = Vec::new();
+        let mut node = list.begin();
+        while let Some(current) = node {
+            values.push(unsafe { current.as_ref().data });
+            node = list.next(current);
+        }
+        values
+    }
+
+    #[test]
+    fn test_cursor_insert_and_remove() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.current(), None);
+        cursor.insert_after(2);
+        cursor.insert_before(4);
+        cursor.move_next();
+        cursor.insert_after(3);
+        cursor.insert_before(1);
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert_eq!(collect(&list), vec![1, 2, 3, 4]);
+
+        let mut cursor = list.cursor_back_mut();
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.remove_current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(collect(&list), vec![2, 3]);
+        assert_eq!(list.pop_back(), Some(3));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        for i in 0..4 {
+            list.push_back(i);
+        }
+        let second = list.next(list.begin().unwrap()).unwrap();
+        assert_eq!(unsafe { list.remove(second) }, 1);
+        assert_eq!(unsafe { list.remove(list.end().unwrap()) }, 3);
+        assert_eq!(unsafe { list.remove(list.begin().unwrap()) }, 0);
+        assert_eq!(collect(&list), vec![2]);
+        assert_eq!(unsafe { list.remove(list.begin().unwrap()) }, 2);
+        assert!(list.begin().is_none() && list.end().is_none());
+    }
+
+    #[test]
+    fn test_cursor_split_after() {
+        let mut list: LinkedList<i32> = LinkedList::new();
+        for i in 0..5 {
+            list.push_back(i);
+        }
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        let mut tail = cursor.split_after();
+        assert!(cursor.split_after().begin().is_none());
+        assert_eq!(collect(&list), vec![0, 1]);
+        assert_eq!(collect(&tail), vec![2, 3, 4]);
+        assert_eq!(tail.pop_front(), Some(2));
+        assert_eq!(list.pop_back(), Some(1));
+
+        let mut cursor = tail.cursor_back_mut();
+        cursor.move_next();
+        let all = cursor.split_after();
+        assert_eq!(collect(&all), vec![3, 4]);
+        assert!(tail.begin().is_none());
+    }
 }
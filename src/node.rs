@@ -0,0 +1,57 @@
+use std::ptr::NonNull;
+
+/// # ADNode
+/// A node of the AAD tape: the adjoint of an operation's result together with
+/// the local derivatives with respect to its `n` arguments and pointers to the
+/// adjoints of those arguments. Both arrays live in the tape's companion pools,
+/// so once the pools have grown to a tape's size, recording a node again after
+/// a rewind doesn't touch the allocator.
+#[derive(Clone, Copy)]
+pub struct ADNode {
+    pub n: usize,
+    pub adjoint: f64,
+    derivatives: NonNull<f64>,
+    adj_ptrs: NonNull<NonNull<f64>>,
+}
+
+impl ADNode {
+    /// # Safety
+    /// `derivatives` and `adj_ptrs` must point to `n` initialized values that
+    /// outlive the node, and every adjoint pointer must be valid for writes
+    /// whenever the node is propagated.
+    pub unsafe fn new(
+        n: usize,
+        derivatives: NonNull<f64>,
+        adj_ptrs: NonNull<NonNull<f64>>,
+    ) -> Self {
+        ADNode {
+            n,
+            adjoint: 0.0,
+            derivatives,
+            adj_ptrs,
+        }
+    }
+
+    /// Local derivatives with respect to the arguments.
+    pub fn derivatives(&self) -> &[f64] {
+        unsafe { std::slice::from_raw_parts(self.derivatives.as_ptr(), self.n) }
+    }
+
+    /// Pointer to this node's adjoint, to be stored by the nodes that use it.
+    pub fn adjoint_ptr(node: NonNull<ADNode>) -> NonNull<f64> {
+        unsafe { NonNull::new_unchecked(std::ptr::addr_of_mut!((*node.as_ptr()).adjoint)) }
+    }
+
+    /// Propagate this node's adjoint to its arguments.
+    pub fn propagate_one(&self) {
+        if self.n == 0 || self.adjoint == 0.0 {
+            return;
+        }
+        unsafe {
+            for i in 0..self.n {
+                let adj = *self.adj_ptrs.as_ptr().add(i);
+                *adj.as_ptr() += *self.derivatives.as_ptr().add(i) * self.adjoint;
+            }
+        }
+    }
+}
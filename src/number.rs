@@ -0,0 +1,265 @@
+use std::{
+    ops::{Add, Div, Mul, Neg, Sub},
+    ptr::NonNull,
+};
+
+use crate::{node::ADNode, tape::Tape};
+
+/// # Number
+/// A value that records every operation applied to it on a `Tape`, so that
+/// its derivatives can be obtained by propagating adjoints backwards.
+#[derive(Clone, Copy)]
+pub struct Number<'t> {
+    value: f64,
+    node: NonNull<ADNode>,
+    tape: &'t Tape,
+}
+
+impl<'t> Number<'t> {
+    /// Create an input on the tape.
+    pub fn new(tape: &'t Tape, value: f64) -> Self {
+        Number {
+            value,
+            node: tape.record_leaf(),
+            tape,
+        }
+    }
+
+    /// Record the result of a unary operation with local derivative `derivative`.
+    fn unary(self, value: f64, derivative: f64) -> Self {
+        Number {
+            value,
+            node: unsafe { self.tape.record(&[(self.node, derivative)]) },
+            tape: self.tape,
+        }
+    }
+
+    /// Record the result of a binary operation with local derivatives `da` and `db`.
+    fn binary(self, rhs: Self, value: f64, da: f64, db: f64) -> Self {
+        assert!(
+            std::ptr::eq(self.tape, rhs.tape),
+            "numbers recorded on different tapes"
+        );
+        Number {
+            value,
+            node: unsafe { self.tape.record(&[(self.node, da), (rhs.node, db)]) },
+            tape: self.tape,
+        }
+    }
+
+    /// Record a function of this number that isn't built in, given the
+    /// function's value and its derivative at this number.
+    pub fn apply(self, value: f64, derivative: f64) -> Self {
+        self.unary(value, derivative)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn adjoint(&self) -> f64 {
+        unsafe { self.node.as_ref().adjoint }
+    }
+
+    pub fn set_adjoint(&self, adjoint: f64) {
+        unsafe { (*self.node.as_ptr()).adjoint = adjoint };
+    }
+
+    /// The node recording this number on the tape.
+    pub fn node(&self) -> NonNull<ADNode> {
+        self.node
+    }
+
+    /// Seed this number's adjoint with one and propagate it through the whole tape.
+    pub fn propagate_to_start(&self) {
+        self.set_adjoint(1.0);
+        self.tape.propagate_all();
+    }
+
+    pub fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.unary(value, value)
+    }
+
+    pub fn ln(self) -> Self {
+        self.unary(self.value.ln(), 1.0 / self.value)
+    }
+
+    pub fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.unary(value, 0.5 / value)
+    }
+
+    pub fn powf(self, exponent: f64) -> Self {
+        self.unary(
+            self.value.powf(exponent),
+            exponent * self.value.powf(exponent - 1.0),
+        )
+    }
+}
+
+impl<'t> Add for Number<'t> {
+    type Output = Number<'t>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.binary(rhs, self.value + rhs.value, 1.0, 1.0)
+    }
+}
+
+impl<'t> Sub for Number<'t> {
+    type Output = Number<'t>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.binary(rhs, self.value - rhs.value, 1.0, -1.0)
+    }
+}
+
+impl<'t> Mul for Number<'t> {
+    type Output = Number<'t>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.binary(rhs, self.value * rhs.value, rhs.value, self.value)
+    }
+}
+
+impl<'t> Div for Number<'t> {
+    type Output = Number<'t>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let inv = 1.0 / rhs.value;
+        self.binary(rhs, self.value * inv, inv, -self.value * inv * inv)
+    }
+}
+
+impl<'t> Neg for Number<'t> {
+    type Output = Number<'t>;
+
+    fn neg(self) -> Self::Output {
+        self.unary(-self.value, -1.0)
+    }
+}
+
+impl<'t> Add<f64> for Number<'t> {
+    type Output = Number<'t>;
+
+    fn add(self, rhs: f64) -> Self::Output {
+        self.unary(self.value + rhs, 1.0)
+    }
+}
+
+impl<'t> Sub<f64> for Number<'t> {
+    type Output = Number<'t>;
+
+    fn sub(self, rhs: f64) -> Self::Output {
+        self.unary(self.value - rhs, 1.0)
+    }
+}
+
+impl<'t> Mul<f64> for Number<'t> {
+    type Output = Number<'t>;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        self.unary(self.value * rhs, rhs)
+    }
+}
+
+impl<'t> Div<f64> for Number<'t> {
+    type Output = Number<'t>;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        self.unary(self.value / rhs, 1.0 / rhs)
+    }
+}
+
+impl<'t> Add<Number<'t>> for f64 {
+    type Output = Number<'t>;
+
+    fn add(self, rhs: Number<'t>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<'t> Sub<Number<'t>> for f64 {
+    type Output = Number<'t>;
+
+    fn sub(self, rhs: Number<'t>) -> Self::Output {
+        rhs.unary(self - rhs.value, -1.0)
+    }
+}
+
+impl<'t> Mul<Number<'t>> for f64 {
+    type Output = Number<'t>;
+
+    fn mul(self, rhs: Number<'t>) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl<'t> Div<Number<'t>> for f64 {
+    type Output = Number<'t>;
+
+    fn div(self, rhs: Number<'t>) -> Self::Output {
+        rhs.unary(self / rhs.value, -self / (rhs.value * rhs.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_number_arithmetic() {
+        let tape = Tape::new();
+        let x = Number::new(&tape, 2.0);
+        let y = Number::new(&tape, 3.0);
+        // f = (x * y - x / y + 1) * -x
+        let f = (x * y - x / y + 1.0) * -x;
+        assert_close(f.value(), -(6.0 - 2.0 / 3.0 + 1.0) * 2.0);
+        f.propagate_to_start();
+        // df/dx = -(2xy - 2x/y + 1), df/dy = -(x^2 + x^2/y^2)
+        assert_close(x.adjoint(), -(12.0 - 4.0 / 3.0 + 1.0));
+        assert_close(y.adjoint(), -(4.0 + 4.0 / 9.0));
+    }
+
+    #[test]
+    fn test_number_functions() {
+        let tape = Tape::new();
+        let x = Number::new(&tape, 1.5);
+        let f = x.exp() + x.ln() + x.sqrt() + x.powf(3.0) + 2.0 / x - 1.0;
+        f.propagate_to_start();
+        let expected =
+            1.5f64.exp() + 1.0 / 1.5 + 0.5 / 1.5f64.sqrt() + 3.0 * 1.5 * 1.5 - 2.0 / (1.5 * 1.5);
+        assert_close(x.adjoint(), expected);
+    }
+
+    #[test]
+    fn test_number_apply() {
+        let tape = Tape::new();
+        let x = Number::new(&tape, 0.5);
+        let f = x.apply(x.value().sin(), x.value().cos()) * 2.0;
+        f.propagate_to_start();
+        assert_close(f.value(), 2.0 * 0.5f64.sin());
+        assert_close(x.adjoint(), 2.0 * 0.5f64.cos());
+    }
+
+    fn square_plus_one(x: f64) -> (f64, f64) {
+        Tape::with_active(|tape| {
+            let x = Number::new(tape, x);
+            let f = x * x + 1.0;
+            f.propagate_to_start();
+            (f.value(), x.adjoint())
+        })
+    }
+
+    #[test]
+    fn test_number_active_tape() {
+        let tape = std::pin::pin!(Tape::new());
+        let _active = tape.as_ref().activate();
+        assert_eq!(square_plus_one(3.0), (10.0, 6.0));
+        assert_eq!(tape.len(), 3);
+    }
+}
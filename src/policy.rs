@@ -0,0 +1,214 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// # Policy
+/// What the crate does when a check finds a broken invariant, such as a
+/// stale handle, a handle from another pool or a push to a full fixed pool.
+/// The default is chosen by the `abort-on-violation` and `error-on-violation`
+/// features and can be overridden at run time with `set_policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Panic with a message describing the violation.
+    Panic,
+    /// Print the violation and abort the process, for builds where unwinding
+    /// isn't an option.
+    Abort,
+    /// Return the violation as an error from the checked operations. The
+    /// operations that can't return one, such as `push`, panic instead.
+    Error,
+}
+
+impl Policy {
+    /// Policy selected by the crate features.
+    pub const DEFAULT: Policy = if cfg!(feature = "abort-on-violation") {
+        Policy::Abort
+    } else if cfg!(feature = "error-on-violation") {
+        Policy::Error
+    } else {
+        Policy::Panic
+    };
+}
+
+const UNSET: u8 = u8::MAX;
+
+static OVERRIDE: AtomicU8 = AtomicU8::new(UNSET);
+
+/// The policy in effect: the run-time override if one is set, the default
+/// selected by the crate features otherwise.
+pub fn policy() -> Policy {
+    match OVERRIDE.load(Ordering::Relaxed) {
+        0 => Policy::Panic,
+        1 => Policy::Abort,
+        2 => Policy::Error,
+        _ => Policy::DEFAULT,
+    }
+}
+
+/// Override the policy for the whole process, or go back to the default
+/// with `None`.
+pub fn set_policy(policy: Option<Policy>) {
+    let value = match policy {
+        Some(Policy::Panic) => 0,
+        Some(Policy::Abort) => 1,
+        Some(Policy::Error) => 2,
+        None => UNSET,
+    };
+    OVERRIDE.store(value, Ordering::Relaxed);
+}
+
+/// # Violation
+/// A broken invariant found by one of the crate's checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// A handle to an element that has been rewound or discarded.
+    StaleHandle,
+    /// A handle resolved with a pool other than the one it came from.
+    ForeignHandle,
+    /// A push to a fixed pool with no room left.
+    PoolFull,
+    /// A tape checkpoint past the end of the tape or in blocks freed since.
+    StaleCheckpoint,
+    /// A tape checkpoint used with a tape other than the one it was taken on.
+    ForeignCheckpoint,
+    /// Two tape checkpoints given in the wrong order.
+    UnorderedCheckpoints,
+    /// A tail or weak reference used with a pool other than the one it was
+    /// taken from.
+    ForeignPool,
+    /// A pool whose internal bookkeeping is inconsistent, found by `validate`.
+    BrokenInvariant,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::StaleHandle => write!(f, "the handle's element is no longer in the pool"),
+            Violation::ForeignHandle => write!(f, "the handle was pushed to another pool"),
+            Violation::PoolFull => write!(f, "the pool is full"),
+            Violation::StaleCheckpoint => write!(f, "the checkpoint is past the end of the tape"),
+            Violation::ForeignCheckpoint => write!(f, "the checkpoint was taken on another tape"),
+            Violation::UnorderedCheckpoints => write!(f, "the checkpoints are out of order"),
+            Violation::ForeignPool => write!(f, "it was taken from another pool"),
+            Violation::BrokenInvariant => write!(f, "the pool's invariants are broken"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// Apply the policy to `violation`: panic or abort, or hand it back for the
+/// caller to return.
+#[track_caller]
+pub fn violate(violation: Violation) -> Violation {
+    match policy() {
+        Policy::Error => violation,
+        _ => fail(format_args!("{violation}")),
+    }
+}
+
+/// Like `violate`, with `details` added to the panic or abort message.
+#[track_caller]
+pub fn violate_with(violation: Violation, details: fmt::Arguments) -> Violation {
+    match policy() {
+        Policy::Error => violation,
+        _ => fail(format_args!("{violation}: {details}")),
+    }
+}
+
+/// Return the violation from the enclosing function, after applying the
+/// policy to it, unless the condition holds.
+macro_rules! ensure {
+    ($condition:expr, $violation:expr, $($details:tt)+) => {
+        if !$condition {
+            return Err($crate::policy::violate_with(
+                $violation,
+                format_args!($($details)+),
+            ));
+        }
+    };
+}
+
+pub(crate) use ensure;
+
+/// Serializes the tests that depend on the policy in effect with the one
+/// that overrides it.
+#[cfg(test)]
+pub(crate) static TEST_POLICY: std::sync::RwLock<()> = std::sync::RwLock::new(());
+
+/// Hold while a test relies on the policy selected by the crate features.
+#[cfg(all(
+    test,
+    not(any(feature = "abort-on-violation", feature = "error-on-violation"))
+))]
+pub(crate) fn default_policy() -> std::sync::RwLockReadGuard<'static, ()> {
+    TEST_POLICY.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Apply the policy to a violation the caller can't return: abort under
+/// `Policy::Abort`, panic with `message` otherwise.
+#[track_caller]
+pub fn fail(message: fmt::Arguments) -> ! {
+    if policy() == Policy::Abort {
+        eprintln!("{message}");
+        std::process::abort();
+    }
+    panic!("{message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{smallobjectpool::SmallObjectPool, tape::Tape};
+
+    // The override is process-wide, so every check of it is in this one test,
+    // which holds off the tests relying on the default until it is restored.
+    #[test]
+    fn test_policy_override() {
+        struct Restore;
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                set_policy(None);
+            }
+        }
+        let _lock = TEST_POLICY.write().unwrap_or_else(|e| e.into_inner());
+        let _restore = Restore;
+        let mut pool = SmallObjectPool::<u32, 4>::new();
+        pool.push(0);
+        pool.mark();
+        let handle = pool.push_handle(1);
+        let other = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(pool.get_checked(&handle), Ok(&1));
+
+        set_policy(Some(Policy::Error));
+        assert_eq!(policy(), Policy::Error);
+        assert_eq!(other.get_checked(&handle), Err(Violation::ForeignHandle));
+        pool.rewind_to_mark();
+        assert_eq!(pool.get_checked(&handle), Err(Violation::StaleHandle));
+        assert!(matches!(
+            pool.subscribe().poll(&other),
+            Err(Violation::ForeignPool)
+        ));
+        let tape = Tape::new();
+        let start = tape.checkpoint();
+        tape.record_leaf();
+        assert_eq!(
+            tape.propagate_between(tape.checkpoint(), start),
+            Err(Violation::UnorderedCheckpoints)
+        );
+        assert_eq!(
+            tape.propagate_between(Tape::new().checkpoint(), start),
+            Err(Violation::ForeignCheckpoint)
+        );
+
+        set_policy(Some(Policy::Panic));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pool.get_checked(&handle).is_ok()
+        }));
+        assert!(result.is_err());
+
+        set_policy(None);
+        assert_eq!(policy(), Policy::DEFAULT);
+    }
+}
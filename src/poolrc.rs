@@ -0,0 +1,254 @@
+use std::{
+    cell::Cell,
+    marker::PhantomData,
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    blockmeta::BlockMeta,
+    growth::Growth,
+    policy::{self, Violation},
+    smallobjectpool::SmallObjectPool,
+};
+
+/// # RcBox
+/// Control block of a `PoolRc`: the count of pointers to the value, stored
+/// next to the value in the pool, and the generation `PoolWeak`s check to
+/// tell the value apart from later ones written to the same slot.
+pub struct RcBox<T> {
+    count: Cell<usize>,
+    generation: u64,
+    pool_id: usize,
+    value: T,
+}
+
+impl<T> RcBox<T> {
+    /// The value.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// The value, through exclusive access to the pool, e.g. `iter_mut`.
+    /// A `PoolRc` borrows its pool, so none can be alive meanwhile.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// A pool holding the values shared through `PoolRc`s.
+pub type RcPool<T, const CAP: usize> = SmallObjectPool<RcBox<T>, CAP>;
+
+/// # PoolRc
+/// A reference-counted pointer to a value in a pool. Cloning and dropping
+/// only update the count; the value is dropped and its slot reused when the
+/// pool is rewound, which the borrow of the pool prevents while any `PoolRc`
+/// to it is alive.
+pub struct PoolRc<'a, T> {
+    inner: NonNull<RcBox<T>>,
+    _pool: PhantomData<&'a RcBox<T>>,
+}
+
+impl<'a, T> PoolRc<'a, T> {
+    /// Move `value` into `pool` and return the first pointer to it.
+    pub fn new_in<const CAP: usize, M: BlockMeta<RcBox<T>>, G: Growth>(
+        pool: &'a SmallObjectPool<RcBox<T>, CAP, M, G>,
+        value: T,
+    ) -> Self {
+        let inner = pool.alloc(RcBox {
+            count: Cell::new(1),
+            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+            pool_id: pool.id(),
+            value,
+        });
+        PoolRc {
+            inner: NonNull::from(inner),
+            _pool: PhantomData,
+        }
+    }
+
+    fn inner(&self) -> &RcBox<T> {
+        // The pool keeps the box alive and unmoved for as long as it is borrowed.
+        unsafe { self.inner.as_ref() }
+    }
+
+    /// Number of pointers to the value.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().count.get()
+    }
+
+    /// Whether both pointers point to the same value.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner == other.inner
+    }
+
+    /// A weak pointer to the value, which doesn't keep the pool borrowed.
+    pub fn downgrade(this: &Self) -> PoolWeak<T> {
+        PoolWeak {
+            inner: this.inner,
+            generation: this.inner().generation,
+            pool_id: this.inner().pool_id,
+        }
+    }
+}
+
+impl<T> Deref for PoolRc<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T> Clone for PoolRc<'_, T> {
+    fn clone(&self) -> Self {
+        let count = &self.inner().count;
+        count.set(count.get() + 1);
+        PoolRc {
+            inner: self.inner,
+            _pool: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for PoolRc<'_, T> {
+    fn drop(&mut self) {
+        let count = &self.inner().count;
+        count.set(count.get() - 1);
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for PoolRc<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// # PoolWeak
+/// A pointer to a value shared through `PoolRc`s that doesn't borrow the
+/// pool, so it may outlive a rewind. `upgrade` checks the slot is still live
+/// and still holds the same value, so a weak pointer into a rewound region
+/// fails to upgrade instead of dangling.
+pub struct PoolWeak<T> {
+    inner: NonNull<RcBox<T>>,
+    generation: u64,
+    pool_id: usize,
+}
+
+impl<T> PoolWeak<T> {
+    /// A `PoolRc` to the value, unless `pool` has been rewound past it
+    /// since. Upgrading with a pool other than the one the value was moved
+    /// into is a violation, handled as the crate's `Policy` says.
+    pub fn upgrade<'a, const CAP: usize, M: BlockMeta<RcBox<T>>, G: Growth>(
+        &self,
+        pool: &'a SmallObjectPool<RcBox<T>, CAP, M, G>,
+    ) -> Result<Option<PoolRc<'a, T>>, Violation> {
+        if self.pool_id != pool.id() {
+            return Err(policy::violate(Violation::ForeignPool));
+        }
+        if !pool.contains_initialized(self.inner) {
+            return Ok(None);
+        }
+        // The slot is live in `pool`, which stays borrowed by the result.
+        let inner = unsafe { self.inner.as_ref() };
+        if inner.generation != self.generation {
+            return Ok(None);
+        }
+        inner.count.set(inner.count.get() + 1);
+        Ok(Some(PoolRc {
+            inner: self.inner,
+            _pool: PhantomData,
+        }))
+    }
+}
+
+impl<T> Clone for PoolWeak<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PoolWeak<T> {}
+
+impl<T> std::fmt::Debug for PoolWeak<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolWeak")
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poolrc_shared_ownership() {
+        let mut pool = RcPool::<String, 4>::new();
+        {
+            let red = PoolRc::new_in(&pool, String::from("red"));
+            let blue = PoolRc::new_in(&pool, String::from("blue"));
+            let cars: Vec<(u32, PoolRc<String>)> =
+                vec![(1, red.clone()), (2, blue.clone()), (3, red.clone())];
+            assert_eq!(PoolRc::strong_count(&red), 3);
+            assert!(PoolRc::ptr_eq(&cars[0].1, &cars[2].1));
+            assert!(!PoolRc::ptr_eq(&cars[0].1, &cars[1].1));
+            assert_eq!(cars[1].1.as_str(), "blue");
+            drop(cars);
+            assert_eq!(PoolRc::strong_count(&red), 1);
+        }
+        // Dropping the last pointer leaves the value to the pool.
+        assert_eq!(pool.len(), 2);
+        pool.rewind();
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_poolrc_get_mut() {
+        let mut pool = RcPool::<u32, 4>::new();
+        let weak = PoolRc::downgrade(&PoolRc::new_in(&pool, 1));
+        *pool.get_mut(0).unwrap().get_mut() += 1;
+        pool.iter_mut().for_each(|rc| *rc.get_mut() *= 10);
+        let value = weak.upgrade(&pool).unwrap().unwrap();
+        assert_eq!((*value, *pool[0].get()), (20, 20));
+        assert_eq!(format!("{value:?}"), "20");
+    }
+
+    #[test]
+    fn test_poolweak_upgrade_after_rewind() {
+        let mut pool = RcPool::<String, 4>::new();
+        let kept = PoolRc::downgrade(&PoolRc::new_in(&pool, String::from("kept")));
+        pool.mark();
+        let rewound = PoolRc::downgrade(&PoolRc::new_in(&pool, String::from("scratch")));
+        assert_eq!(
+            rewound
+                .upgrade(&pool)
+                .unwrap()
+                .as_deref()
+                .map(String::as_str),
+            Some("scratch")
+        );
+        pool.rewind_to_mark();
+        assert!(rewound.upgrade(&pool).unwrap().is_none());
+        // A new value in the same slot isn't mistaken for the old one.
+        let reused = PoolRc::new_in(&pool, String::from("reused"));
+        assert!(rewound.upgrade(&pool).unwrap().is_none());
+        let upgraded = kept.upgrade(&pool).unwrap().unwrap();
+        assert_eq!(upgraded.as_str(), "kept");
+        assert_eq!(PoolRc::strong_count(&upgraded), 1);
+        drop(reused);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "it was taken from another pool")]
+    fn test_poolweak_upgrade_other_pool() {
+        let _policy = policy::default_policy();
+        let pool = RcPool::<String, 4>::new();
+        let weak = PoolRc::downgrade(&PoolRc::new_in(&pool, String::from("kept")));
+        let _ = weak.upgrade(&RcPool::<String, 4>::new());
+    }
+}
@@ -0,0 +1,183 @@
+use std::ops::{Index, IndexMut};
+
+use crate::smallobjectpool::{Iter, IterMut, SmallObjectPool};
+
+/// # PoolVec
+/// The subset of `Vec`'s API that a pool can offer, backed by a
+/// `SmallObjectPool`, so that code written against a `Vec` can switch to
+/// pooled storage by changing a type. Unlike a `Vec`, growing never moves
+/// the elements and the storage isn't contiguous, so there is no `as_slice`.
+pub struct PoolVec<T, const CAP: usize> {
+    pool: SmallObjectPool<T, CAP>,
+}
+
+impl<T, const CAP: usize> PoolVec<T, CAP> {
+    pub fn new() -> Self {
+        PoolVec {
+            pool: SmallObjectPool::new(),
+        }
+    }
+
+    /// Create an empty vector with room for `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        PoolVec {
+            pool: SmallObjectPool::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.pool.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.pool.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.pool.capacity()
+    }
+
+    // Only single elements are pushed, so indices are the pool's slot indices.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.pool.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.pool.get_mut(index)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.iter().next_back()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, CAP> {
+        self.pool.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, CAP> {
+        self.pool.iter_mut()
+    }
+
+    /// Drop the elements from `len` on, keeping the blocks for reuse.
+    pub fn truncate(&mut self, len: usize) {
+        if let Some(element) = self.pool.get(len) {
+            let position = self.pool.locate(element.into()).unwrap();
+            self.pool.rewind_to(position);
+        }
+    }
+
+    /// Drop every element, keeping the blocks for reuse.
+    pub fn clear(&mut self) {
+        self.pool.rewind();
+    }
+
+    pub fn into_pool(self) -> SmallObjectPool<T, CAP> {
+        self.pool
+    }
+}
+
+impl<T, const CAP: usize> Default for PoolVec<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize> Index<usize> for PoolVec<T, CAP> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.pool[index]
+    }
+}
+
+impl<T, const CAP: usize> IndexMut<usize> for PoolVec<T, CAP> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.pool[index]
+    }
+}
+
+impl<T, const CAP: usize> Extend<T> for PoolVec<T, CAP> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.pool.extend(iter);
+    }
+}
+
+impl<T, const CAP: usize> FromIterator<T> for PoolVec<T, CAP> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PoolVec {
+            pool: SmallObjectPool::from_iter(iter),
+        }
+    }
+}
+
+impl<'a, T, const CAP: usize> IntoIterator for &'a PoolVec<T, CAP> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, CAP>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: std::fmt::Debug, const CAP: usize> std::fmt::Debug for PoolVec<T, CAP> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for code written against `Vec`.
+    fn running_max(values: &mut PoolVec<i64, 4>, input: &[i64]) {
+        for &x in input {
+            let max = values.last().map_or(x, |&last| last.max(x));
+            values.push(max);
+        }
+    }
+
+    #[test]
+    fn test_pool_vec() {
+        let mut values = PoolVec::<i64, 4>::new();
+        running_max(&mut values, &[3, 1, 4, 1, 5, 9, 2, 6]);
+        assert_eq!(values.len(), 8);
+        assert_eq!(format!("{values:?}"), "[3, 3, 4, 4, 5, 9, 9, 9]");
+        assert_eq!((values[5], values.get(8)), (9, None));
+        values[0] = 0;
+        assert_eq!(values.pop(), Some(9));
+        values.truncate(5);
+        assert_eq!(values.len(), 5);
+        assert_eq!(values.iter().copied().collect::<Vec<_>>(), [0, 3, 4, 4, 5]);
+        values.truncate(10);
+        assert_eq!(values.len(), 5);
+        values.clear();
+        assert!(values.is_empty() && values.first().is_none());
+        assert_eq!(values.capacity(), 8);
+    }
+
+    #[test]
+    fn test_pool_vec_drops() {
+        let value = std::rc::Rc::new(());
+        let mut values: PoolVec<_, 4> = std::iter::repeat_n(value.clone(), 10).collect();
+        values.truncate(3);
+        assert_eq!(values.len(), 3);
+        assert_eq!(std::rc::Rc::strong_count(&value), 4);
+        drop(values.pop());
+        assert_eq!(std::rc::Rc::strong_count(&value), 3);
+        drop(values);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+}
@@ -1,4 +1,28 @@
 pub use crate::arraylike::*;
+pub use crate::blockmeta::*;
+pub use crate::blockpool::*;
+pub use crate::blocksource::*;
+pub use crate::builder::*;
+pub use crate::concurrentpool::*;
+pub use crate::dynpool::*;
+pub use crate::growth::*;
+pub use crate::handle::*;
+pub use crate::index::*;
+pub use crate::inlinepool::*;
+pub use crate::lazy::*;
 pub use crate::linkedlist::*;
+pub use crate::node::*;
+pub use crate::number::*;
+pub use crate::policy::*;
+pub use crate::poolrc::*;
+pub use crate::poolvec::*;
 pub use crate::ptrbased::*;
+pub use crate::rawpool::*;
+pub use crate::relocate::*;
+pub use crate::shardedpool::*;
+pub use crate::slotmap::*;
 pub use crate::smallobjectpool::*;
+pub use crate::staticpool::*;
+pub use crate::syncpool::*;
+pub use crate::tail::*;
+pub use crate::tape::*;
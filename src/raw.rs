@@ -0,0 +1,275 @@
+//! The crate's `unsafe` entry points in one place. Each function states its
+//! preconditions and is paired with a `debug_validate_*` function that checks
+//! them at runtime in debug builds; the functions here call their validators,
+//! so debug test runs catch misuse that would otherwise be undefined behavior.
+//! The pool's own unsafe methods are private to the crate, so this module is
+//! the only way to reach them.
+
+use std::{mem::MaybeUninit, ptr::NonNull};
+
+use crate::{
+    arraylike::ArrayLike, blockmeta::BlockMeta, growth::Growth, node::ADNode,
+    smallobjectpool::SmallObjectPool, tape::Tape,
+};
+
+/// Check that `ptr` points to a live slot of `pool`.
+pub fn debug_validate_live_ptr<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &SmallObjectPool<T, CAP, M, G>,
+    ptr: NonNull<T>,
+) {
+    debug_assert!(
+        pool.contains(ptr),
+        "pointer is not in the live part of the pool"
+    );
+}
+
+/// Check that the `n` slots starting at `ptr` are live and in a single block of `pool`.
+pub fn debug_validate_contiguous<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &SmallObjectPool<T, CAP, M, G>,
+    ptr: NonNull<T>,
+    n: usize,
+) {
+    if n == 0 {
+        return;
+    }
+    let first = pool.locate(ptr);
+    let last = pool.locate(unsafe { ptr.add(n - 1) });
+    debug_assert!(
+        first.is_some() && last.is_some(),
+        "slots are not in the live part of the pool"
+    );
+    debug_assert_eq!(
+        first.map(|position| position.block_index()),
+        last.map(|position| position.block_index()),
+        "slots span two blocks"
+    );
+}
+
+/// Check that `index` is a valid slot of a block of `CAP` slots.
+pub fn debug_validate_index<const CAP: usize>(index: usize) {
+    debug_assert!(index < CAP, "index {index} out of bounds for {CAP} slots");
+}
+
+/// Check that a block of `CAP` slots can hold `len` values.
+pub fn debug_validate_len<const CAP: usize>(len: usize) {
+    debug_assert!(len <= CAP, "length {len} exceeds {CAP} slots");
+}
+
+/// Check that `n` slots are free in the current block of `pool`.
+pub fn debug_validate_free<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &SmallObjectPool<T, CAP, M, G>,
+    n: usize,
+) {
+    debug_assert!(
+        n <= pool.free_in_block(),
+        "{n} slots are not free in the current block"
+    );
+}
+
+/// Check that every argument is a live node of `tape`.
+pub fn debug_validate_arguments(tape: &Tape, arguments: &[(NonNull<ADNode>, f64)]) {
+    for (node, _) in arguments {
+        debug_assert!(
+            tape.contains(*node),
+            "argument is not a live node of the tape"
+        );
+    }
+}
+
+/// Push `value` and return a pointer to it.
+///
+/// # Safety
+/// The pointer must not be used after the pool is rewound past it or dropped.
+pub unsafe fn push_to_ptr<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+    value: T,
+) -> NonNull<T> {
+    let ptr = pool.push_to_ptr(value);
+    debug_validate_live_ptr(pool, ptr);
+    ptr
+}
+
+/// Reserve a slot and return a pointer to it.
+///
+/// # Safety
+/// The slot must be written before it is read or dropped, and the pointer
+/// must not be used after the pool is rewound past it or dropped.
+pub unsafe fn emplace_back<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+) -> NonNull<T> {
+    let ptr = pool.emplace_back();
+    debug_validate_live_ptr(pool, ptr);
+    ptr
+}
+
+/// Reserve `N` contiguous slots and return a pointer to the first one.
+///
+/// # Safety
+/// As for `emplace_back`, for each of the `N` slots.
+pub unsafe fn emplace_back_multi<
+    T,
+    const CAP: usize,
+    M: BlockMeta<T>,
+    G: Growth,
+    const N: usize,
+>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+) -> NonNull<T> {
+    let ptr = pool.emplace_back_multi::<N>();
+    debug_validate_contiguous(pool, ptr, N);
+    ptr
+}
+
+/// Record that the `n` slots starting at `ptr`, reserved with `emplace_back`
+/// or `emplace_back_multi`, have been written.
+///
+/// # Safety
+/// The `n` slots must be initialized.
+pub unsafe fn commit_emplaced<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+    ptr: NonNull<T>,
+    n: usize,
+) {
+    debug_validate_contiguous(pool, ptr, n);
+    pool.commit_emplaced(ptr, n);
+}
+
+/// The free slots of the current block of `pool`, to be written in place
+/// and then published with `commit`. Moves to the next block first if the
+/// current one is full, so the slice is never empty.
+pub fn raw_slots_mut<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+) -> &mut [MaybeUninit<T>] {
+    pool.raw_slots_mut()
+}
+
+/// Move the cursor of `pool` over the first `n` slots of `raw_slots_mut`.
+///
+/// # Safety
+/// The `n` slots must be initialized.
+pub unsafe fn commit<T, const CAP: usize, M: BlockMeta<T>, G: Growth>(
+    pool: &mut SmallObjectPool<T, CAP, M, G>,
+    n: usize,
+) {
+    debug_validate_free(pool, n);
+    pool.commit(n);
+}
+
+/// Record a node with the given arguments and local derivatives.
+///
+/// # Safety
+/// Every argument must be a node of `tape` that has not been rewound.
+pub unsafe fn record(tape: &Tape, arguments: &[(NonNull<ADNode>, f64)]) -> NonNull<ADNode> {
+    debug_validate_arguments(tape, arguments);
+    tape.record(arguments)
+}
+
+/// Write `value` at `index` without bounds checking.
+///
+/// # Safety
+/// `index` must be smaller than `CAP`.
+pub unsafe fn insert_unchecked<T, const CAP: usize>(
+    block: &mut ArrayLike<T, CAP>,
+    index: usize,
+    value: T,
+) {
+    debug_validate_index::<CAP>(index);
+    block.insert_unchecked(index, value);
+}
+
+/// Set the number of used slots of a block.
+///
+/// # Safety
+/// The first `len` slots must be initialized.
+pub unsafe fn set_len<T, const CAP: usize>(block: &mut ArrayLike<T, CAP>, len: usize) {
+    debug_validate_len::<CAP>(len);
+    block.set_len(len);
+}
+
+/// Move the cursor of `block` over the first `n` slots of `raw_slots_mut`.
+///
+/// # Safety
+/// The `n` slots must be initialized.
+pub unsafe fn commit_block<T, const CAP: usize>(block: &mut ArrayLike<T, CAP>, n: usize) {
+    debug_validate_len::<CAP>(block.len() + n);
+    block.commit(n);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_push_and_emplace() {
+        let mut pool = SmallObjectPool::<u32, 4>::new();
+        let ptr = unsafe { push_to_ptr(&mut pool, 1) };
+        assert_eq!(unsafe { *ptr.as_ptr() }, 1);
+        let slots = unsafe { emplace_back_multi::<_, 4, _, _, 3>(&mut pool) };
+        debug_validate_contiguous(&pool, slots, 3);
+    }
+
+    #[test]
+    fn test_raw_non_copy() {
+        let mut pool = SmallObjectPool::<String, 4>::new();
+        let ptr = unsafe { push_to_ptr(&mut pool, "pushed".to_string()) };
+        let slot = unsafe { emplace_back(&mut pool) };
+        unsafe {
+            slot.as_ptr().write("emplaced".to_string());
+            commit_emplaced(&mut pool, slot, 1);
+        }
+        assert_eq!(unsafe { ptr.as_ref() }, "pushed");
+        assert_eq!(
+            pool.iter().next_back().map(String::as_str),
+            Some("emplaced")
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not in the live part")]
+    fn test_raw_validate_stale_ptr() {
+        let mut pool = SmallObjectPool::<u32, 4>::new();
+        let ptr = unsafe { push_to_ptr(&mut pool, 1) };
+        pool.rewind();
+        debug_validate_live_ptr(&pool, ptr);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a live node")]
+    fn test_raw_record_foreign_argument() {
+        let tape = Tape::new();
+        let other = Tape::new();
+        let x = other.record_leaf();
+        unsafe { record(&tape, &[(x, 1.0)]) };
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of bounds")]
+    fn test_raw_insert_out_of_bounds() {
+        let mut block = ArrayLike::<u32, 2>::new();
+        unsafe { insert_unchecked(&mut block, 2, 1) };
+    }
+
+    #[test]
+    fn test_raw_decode_in_place() {
+        let bytes = [3u8, 1, 4, 1, 5, 9, 2, 6, 5];
+        let mut pool = SmallObjectPool::<u16, 4>::new();
+        let mut rest = &bytes[..];
+        while !rest.is_empty() {
+            let slots = raw_slots_mut(&mut pool);
+            let n = slots.len().min(rest.len());
+            for (slot, byte) in slots.iter_mut().zip(&rest[..n]) {
+                slot.write(u16::from(*byte) * 10);
+            }
+            unsafe { commit(&mut pool, n) };
+            rest = &rest[n..];
+        }
+        assert_eq!(
+            pool.iter().copied().collect::<Vec<_>>(),
+            bytes.iter().map(|&b| u16::from(b) * 10).collect::<Vec<_>>()
+        );
+        pool.validate().unwrap();
+    }
+}
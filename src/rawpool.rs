@@ -0,0 +1,286 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+    ptr::NonNull,
+};
+
+use crate::blocksource::{BlockMemory, BlockSource};
+
+/// # RawBlock
+/// A block of `CAP` untyped bytes, aligned for any primitive type.
+#[repr(C, align(16))]
+pub struct RawBlock<const CAP: usize> {
+    data: [MaybeUninit<u8>; CAP],
+}
+
+impl<const CAP: usize> RawBlock<CAP> {
+    pub fn new() -> Self {
+        RawBlock {
+            data: [const { MaybeUninit::uninit() }; CAP],
+        }
+    }
+}
+
+impl<const CAP: usize> Default for RawBlock<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # RawPool
+/// A table of blocks of untyped bytes, for payloads of different sizes and
+/// alignments. Allocations are bump-allocated inside `CAP`-byte blocks and
+/// released together with `rewind` / `rewind_to_mark`.
+pub struct RawPool<const CAP: usize> {
+    data: UnsafeCell<Vec<BlockMemory>>,
+    current_block: Cell<Option<usize>>,
+    offset: Cell<usize>,
+    marked_block: Option<usize>,
+    marked_offset: usize,
+}
+
+// The pool owns its blocks and never reads or drops what is written to them.
+// It isn't `Sync`: `alloc` moves the cursor through a shared borrow.
+unsafe impl<const CAP: usize> Send for RawPool<CAP> {}
+
+impl<const CAP: usize> RawPool<CAP> {
+    pub fn new() -> Self {
+        RawPool {
+            data: UnsafeCell::new(Vec::new()),
+            current_block: Cell::new(None),
+            offset: Cell::new(0),
+            marked_block: None,
+            marked_offset: 0,
+        }
+    }
+
+    fn blocks(&self) -> &[BlockMemory] {
+        unsafe { &*self.data.get() }
+    }
+
+    /// Number of blocks held by the pool
+    pub fn block_count(&self) -> usize {
+        self.blocks().len()
+    }
+
+    /// First byte of the block with the given index.
+    fn begin(&self, block: usize) -> *mut u8 {
+        self.blocks()[block].as_ptr().as_ptr()
+    }
+
+    /// Move to the next block, allocating it if needed
+    fn next_block(&self) {
+        let next = self.current_block.get().map_or(0, |block| block + 1);
+        if next == self.blocks().len() {
+            // No reference into the table is alive while a block is being appended.
+            unsafe { (*self.data.get()).push(BlockMemory::new(self.block_layout())) };
+        }
+        self.current_block.set(Some(next));
+        self.offset.set(0);
+    }
+
+    /// Offset in the current block at which `layout` would be placed, if it fits.
+    fn fit(&self, layout: Layout) -> Option<usize> {
+        let base = self.begin(self.current_block.get()?) as usize;
+        let start = (base + self.offset.get()).next_multiple_of(layout.align()) - base;
+        (start + layout.size() <= CAP).then_some(start)
+    }
+
+    /// Allocate memory for `layout`, or return `None` if it can't fit in a block.
+    pub fn try_alloc(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.size() == 0 {
+            return NonNull::new(std::ptr::without_provenance_mut(layout.align()));
+        }
+        if layout.size() > CAP {
+            return None;
+        }
+        let start = match self.fit(layout) {
+            Some(start) => start,
+            None => {
+                self.next_block();
+                self.fit(layout)?
+            }
+        };
+        self.offset.set(start + layout.size());
+        let block = self.current_block.get().unwrap();
+        NonNull::new(unsafe { self.begin(block).add(start) })
+    }
+
+    /// Allocate memory for `layout`.
+    ///
+    /// # Panics
+    /// If `layout` doesn't fit in a single block.
+    pub fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        self.try_alloc(layout)
+            .unwrap_or_else(|| panic!("{layout:?} does not fit in blocks of {CAP} bytes"))
+    }
+
+    /// Allocate memory for a `U` and move `value` into it. The value is never
+    /// dropped: rewinding or dropping the pool only reclaims its memory.
+    pub fn alloc_value<U>(&self, value: U) -> NonNull<U> {
+        let ptr = self.alloc(Layout::new::<U>()).cast::<U>();
+        unsafe { ptr.as_ptr().write(value) };
+        ptr
+    }
+
+    /// Free the blocks past the current block and the mark, returning how
+    /// many were freed.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let mut count = 0;
+        while self.take_free_block().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Rewind to the first block
+    pub fn rewind(&mut self) {
+        self.current_block
+            .set((!self.blocks().is_empty()).then_some(0));
+        self.offset.set(0);
+    }
+
+    /// Mark the current block and offset
+    pub fn mark(&mut self) {
+        self.marked_block = self.current_block.get();
+        self.marked_offset = self.offset.get();
+    }
+
+    /// Rewind to the marked block and offset
+    pub fn rewind_to_mark(&mut self) {
+        match self.marked_block {
+            Some(block) => {
+                self.current_block.set(Some(block));
+                self.offset.set(self.marked_offset);
+            }
+            None => self.rewind(),
+        }
+    }
+}
+
+impl<const CAP: usize> BlockSource for RawPool<CAP> {
+    fn block_layout(&self) -> Layout {
+        Layout::new::<RawBlock<CAP>>()
+    }
+
+    /// Give away the last block, unless the cursor or the mark is in it.
+    fn take_free_block(&mut self) -> Option<BlockMemory> {
+        let last = self.blocks().len().checked_sub(1)?;
+        let in_use = [self.current_block.get(), self.marked_block]
+            .into_iter()
+            .flatten()
+            .any(|block| block >= last);
+        if in_use {
+            return None;
+        }
+        self.data.get_mut().pop()
+    }
+
+    fn add_free_block(&mut self, block: BlockMemory) -> Result<(), BlockMemory> {
+        if !block.fits(self.block_layout()) {
+            return Err(block);
+        }
+        self.data.get_mut().push(block);
+        Ok(())
+    }
+}
+
+impl<const CAP: usize> Default for RawPool<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_pool_alignment() {
+        let pool = RawPool::<256>::new();
+        pool.alloc(Layout::new::<u8>());
+        let a = pool.alloc(Layout::new::<u64>());
+        assert_eq!(a.as_ptr() as usize % 8, 0);
+        pool.alloc(Layout::new::<u8>());
+        let b = pool.alloc(Layout::from_size_align(8, 64).unwrap());
+        assert_eq!(b.as_ptr() as usize % 64, 0);
+    }
+
+    #[test]
+    fn test_raw_pool_mixed_payloads() {
+        let pool = RawPool::<64>::new();
+        let a = pool.alloc_value(1.5f64);
+        let b = pool.alloc_value([1u32, 2, 3]);
+        let c = pool.alloc_value(7u8);
+        let d = pool.alloc_value([0u64; 6]);
+        unsafe {
+            assert_eq!(*a.as_ref(), 1.5);
+            assert_eq!(*b.as_ref(), [1, 2, 3]);
+            assert_eq!(*c.as_ref(), 7);
+            assert_eq!(*d.as_ref(), [0; 6]);
+        }
+    }
+
+    #[test]
+    fn test_raw_pool_rewind_to_mark() {
+        let mut pool = RawPool::<32>::new();
+        pool.alloc(Layout::new::<u32>());
+        pool.mark();
+        let first = pool.alloc(Layout::new::<u64>());
+        for _ in 0..10 {
+            pool.alloc(Layout::new::<u64>());
+        }
+        pool.rewind_to_mark();
+        assert_eq!(pool.alloc(Layout::new::<u64>()), first);
+        pool.rewind();
+        let start = pool.alloc(Layout::new::<u8>());
+        assert_eq!(start.as_ptr() as usize % 16, 0);
+    }
+
+    #[test]
+    fn test_raw_pool_too_large() {
+        let pool = RawPool::<16>::new();
+        assert!(pool.try_alloc(Layout::new::<[u8; 17]>()).is_none());
+        assert!(pool.try_alloc(Layout::new::<[u8; 16]>()).is_some());
+    }
+
+    #[test]
+    fn test_raw_pool_free_blocks() {
+        let mut pool = RawPool::<32>::new();
+        for _ in 0..4 {
+            pool.alloc(Layout::new::<[u8; 32]>());
+        }
+        assert!(pool.take_free_block().is_none());
+        pool.rewind();
+        pool.alloc(Layout::new::<u8>());
+        let block = pool.take_free_block().unwrap();
+        assert_eq!(pool.block_count(), 3);
+        assert!(pool.add_free_block(block).is_ok());
+        let small = BlockMemory::new(Layout::from_size_align(16, 16).unwrap());
+        assert!(pool.add_free_block(small).is_err());
+        let mut other = RawPool::<16>::new();
+        assert_eq!(pool.donate_free_blocks(&mut other), 3);
+        assert_eq!(pool.block_count(), 1);
+        for _ in 0..3 {
+            other.alloc(Layout::new::<[u8; 16]>());
+        }
+        assert_eq!(other.block_count(), 3);
+    }
+
+    #[test]
+    fn test_raw_pool_shrink_to_fit() {
+        let mut pool = RawPool::<16>::new();
+        pool.alloc(Layout::new::<u8>());
+        pool.mark();
+        for _ in 0..4 {
+            pool.alloc(Layout::new::<[u8; 16]>());
+        }
+        pool.rewind_to_mark();
+        assert_eq!(pool.shrink_to_fit(), 4);
+        assert_eq!(pool.block_count(), 1);
+        pool.rewind_to_mark();
+        pool.alloc(Layout::new::<[u8; 8]>());
+        assert_eq!(pool.block_count(), 1);
+    }
+}
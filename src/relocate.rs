@@ -0,0 +1,21 @@
+use crate::handle::PoolHandle;
+
+/// # OnRelocate
+/// Notified when a pool moves an element from one slot to another, with a
+/// handle to the slot it left and one to the slot it now lives in, so that
+/// handles held outside the pool can be swapped for the new one instead of
+/// silently resolving to nothing. The old handle stops resolving once the
+/// move is done, like any handle to a removed element.
+pub trait OnRelocate<T> {
+    fn relocated(&mut self, old: PoolHandle<T>, new: PoolHandle<T>);
+}
+
+impl<T> OnRelocate<T> for () {
+    fn relocated(&mut self, _old: PoolHandle<T>, _new: PoolHandle<T>) {}
+}
+
+impl<T, F: FnMut(PoolHandle<T>, PoolHandle<T>)> OnRelocate<T> for F {
+    fn relocated(&mut self, old: PoolHandle<T>, new: PoolHandle<T>) {
+        self(old, new)
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    mem,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+use crate::smallobjectpool::SmallObjectPool;
+
+/// # ShardedPool
+/// A pool split into one `SmallObjectPool` per thread, for a parallel phase
+/// followed by a serial one, such as generating paths on several threads and
+/// aggregating them on one. Each thread takes its own shard with `local` and
+/// pushes to it without any synchronization; the shard joins the pool when
+/// it is dropped. Once every shard is back, the owner iterates over or drains
+/// all of them, in the order they were returned.
+pub struct ShardedPool<T, const CAP: usize> {
+    shards: Mutex<Vec<SmallObjectPool<T, CAP>>>,
+}
+
+impl<T, const CAP: usize> ShardedPool<T, CAP> {
+    /// Create a pool with no shards yet.
+    pub fn new() -> Self {
+        ShardedPool {
+            shards: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A new shard for the calling thread, returned to the pool when dropped.
+    pub fn local(&self) -> LocalPool<'_, T, CAP> {
+        LocalPool {
+            pool: SmallObjectPool::new(),
+            owner: self,
+        }
+    }
+
+    fn shards_mut(&mut self) -> &mut Vec<SmallObjectPool<T, CAP>> {
+        self.shards.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of shards returned to the pool
+    pub fn shard_count(&mut self) -> usize {
+        self.shards_mut().len()
+    }
+
+    /// Number of elements in all the shards
+    pub fn len(&mut self) -> usize {
+        self.shards_mut().iter().map(SmallObjectPool::len).sum()
+    }
+
+    /// Whether none of the shards holds an element
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the elements of every shard, shard by shard. Taking the
+    /// pool exclusively ensures every shard has been returned.
+    pub fn iter(&mut self) -> impl Iterator<Item = &T> {
+        self.shards_mut().iter().flat_map(SmallObjectPool::iter)
+    }
+
+    /// Take the shards out of the pool, leaving it empty for the next phase.
+    pub fn drain(&mut self) -> impl Iterator<Item = SmallObjectPool<T, CAP>> + '_ {
+        self.shards_mut().drain(..)
+    }
+
+    /// The shards returned to the pool, in the order they were returned.
+    pub fn into_shards(self) -> Vec<SmallObjectPool<T, CAP>> {
+        self.shards.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T, const CAP: usize> Default for ShardedPool<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # LocalPool
+/// A thread's shard of a `ShardedPool`, used like any `SmallObjectPool`.
+pub struct LocalPool<'a, T, const CAP: usize> {
+    pool: SmallObjectPool<T, CAP>,
+    owner: &'a ShardedPool<T, CAP>,
+}
+
+impl<T, const CAP: usize> Deref for LocalPool<'_, T, CAP> {
+    type Target = SmallObjectPool<T, CAP>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pool
+    }
+}
+
+impl<T, const CAP: usize> DerefMut for LocalPool<'_, T, CAP> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pool
+    }
+}
+
+impl<T, const CAP: usize> Drop for LocalPool<'_, T, CAP> {
+    fn drop(&mut self) {
+        let pool = mem::take(&mut self.pool);
+        let mut shards = self.owner.shards.lock().unwrap_or_else(|e| e.into_inner());
+        shards.push(pool);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_shardedpool_parallel_then_serial() {
+        let mut paths = ShardedPool::<Vec<f64>, 8>::new();
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let paths = &paths;
+                scope.spawn(move || {
+                    let mut local = paths.local();
+                    for i in 0..25 {
+                        local.push(vec![worker as f64, i as f64]);
+                    }
+                });
+            }
+        });
+        assert_eq!((paths.shard_count(), paths.len()), (4, 100));
+        let total: f64 = paths.iter().map(|path| path.iter().sum::<f64>()).sum();
+        assert_eq!(total, 25.0 * 6.0 + 4.0 * 300.0);
+
+        let shards: Vec<_> = paths.drain().collect();
+        assert!(shards.iter().all(|shard| shard.len() == 25));
+        assert!(paths.is_empty());
+        drop(paths.local());
+        assert_eq!(paths.into_shards().len(), 1);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    blocksource::{BlockRecycler, BlockSource},
+    smallobjectpool::SmallObjectPool,
+};
+
+/// # SlotKey
+/// Key returned by `PoolSlotMap::insert`. The version tells a key apart from
+/// keys to earlier values stored in the same slot, so stale keys miss.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SlotKey {
+    index: usize,
+    version: u32,
+}
+
+/// # PoolSlotMap
+/// A map with versioned keys in the style of the `slotmap` crate, storing its
+/// values in a `SmallObjectPool`. Removed slots are reused by later inserts.
+///
+/// The map counts the live values of each block, and once the last blocks
+/// hold none, gives them to its `BlockRecycler`, so that memory follows the
+/// number of live values under churn instead of their peak. A recycler
+/// with room for a few blocks saves allocating them again when the map
+/// grows back. Slot versions are kept apart from the blocks, so keys into
+/// released slots still miss.
+pub struct PoolSlotMap<T, const CAP: usize> {
+    values: SmallObjectPool<Option<T>, CAP>,
+    /// Version of every slot ever pushed, even once its block is released.
+    /// Odd versions are occupied, even versions are vacant. A slot whose
+    /// version wraps around to 0 is retired rather than reused, as its next
+    /// key would match the first one it handed out.
+    versions: Vec<u32>,
+    /// Number of live values in each block of `values`.
+    live: Vec<usize>,
+    free: Vec<usize>,
+    recycler: BlockRecycler,
+    len: usize,
+}
+
+impl<T, const CAP: usize> PoolSlotMap<T, CAP> {
+    /// Create an empty map that frees the blocks it releases.
+    pub fn new() -> Self {
+        let layout = SmallObjectPool::<Option<T>, CAP>::alloc_layout();
+        Self::with_recycler(BlockRecycler::with_limit(layout, 0))
+    }
+
+    /// Create an empty map that gives the blocks it releases to `recycler`
+    /// and takes blocks from it before allocating new ones.
+    pub fn with_recycler(recycler: BlockRecycler) -> Self {
+        PoolSlotMap {
+            values: SmallObjectPool::new(),
+            versions: Vec::new(),
+            live: Vec::new(),
+            free: Vec::new(),
+            recycler,
+            len: 0,
+        }
+    }
+
+    /// Number of values in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of blocks held by the map.
+    pub fn block_count(&self) -> usize {
+        self.values.block_count()
+    }
+
+    /// The recycler holding the blocks released by the map.
+    pub fn recycler(&self) -> &BlockRecycler {
+        &self.recycler
+    }
+
+    /// Drop the values and hand the map's blocks over to its recycler.
+    pub fn into_recycler(self) -> BlockRecycler {
+        let mut recycler = self.recycler;
+        self.values.recycle(&mut recycler);
+        recycler
+    }
+
+    /// Insert a value and return its key.
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.values[index] = Some(value);
+                index
+            }
+            None => self.push(value),
+        };
+        // Free slots have an even version below `u32::MAX`.
+        self.versions[index] += 1;
+        self.live[index / CAP] += 1;
+        self.len += 1;
+        SlotKey {
+            index,
+            version: self.versions[index],
+        }
+    }
+
+    /// Push a new slot after the last one, skipping retired slots, and
+    /// return its index.
+    fn push(&mut self, value: T) -> usize {
+        loop {
+            let index = self.values.len();
+            if index == self.values.capacity() {
+                if let Some(block) = self.recycler.take_free_block() {
+                    // Blocks that don't fit are freed.
+                    let _ = self.values.add_free_block(block);
+                }
+            }
+            if index / CAP == self.live.len() {
+                self.live.push(0);
+            }
+            match self.versions.get(index) {
+                Some(0) => self.values.push(None),
+                Some(_) => {
+                    self.values.push(Some(value));
+                    return index;
+                }
+                None => {
+                    self.versions.push(0);
+                    self.values.push(Some(value));
+                    return index;
+                }
+            }
+        }
+    }
+
+    /// Remove the value of `key`, if it is still in the map.
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let value = self.slot_mut(key)?.take()?;
+        let version = &mut self.versions[key.index];
+        *version = version.wrapping_add(1);
+        if *version != 0 {
+            self.free.push(key.index);
+        }
+        let block = key.index / CAP;
+        self.live[block] -= 1;
+        if self.live[block] == 0 && block + 1 == self.live.len() {
+            self.release_empty_blocks();
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Drop the slots of the last blocks without live values and give the
+    /// blocks to the recycler.
+    fn release_empty_blocks(&mut self) {
+        while self.live.last() == Some(&0) {
+            self.live.pop();
+        }
+        let len = self.live.len() * CAP;
+        match len.checked_sub(1).and_then(|last| self.values.get(last)) {
+            Some(last) => {
+                // Right after the last slot kept, so that the cursor stays in
+                // its block and the blocks after it can be given away.
+                let mut position = self.values.locate(last.into()).unwrap();
+                position.space = unsafe { position.space.add(1) };
+                self.values.rewind_to(position);
+            }
+            None => self.values.rewind(),
+        }
+        self.free.retain(|&index| index < len);
+        while let Some(block) = self.values.take_free_block() {
+            // Blocks the recycler has no room for are freed.
+            let _ = self.recycler.add_free_block(block);
+        }
+    }
+
+    pub fn contains_key(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        if self.versions.get(key.index) != Some(&key.version) {
+            return None;
+        }
+        self.values.get(key.index)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        self.slot_mut(key)?.as_mut()
+    }
+
+    fn slot_mut(&mut self, key: SlotKey) -> Option<&mut Option<T>> {
+        if self.versions.get(key.index) != Some(&key.version) {
+            return None;
+        }
+        self.values.get_mut(key.index)
+    }
+
+    /// Iterate over the keys and values of the map, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.values.iter().enumerate().filter_map(|(index, value)| {
+            let key = SlotKey {
+                index,
+                version: self.versions[index],
+            };
+            value.as_ref().map(|value| (key, value))
+        })
+    }
+
+    /// Iterate over the values of the map, in slot order.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.values.iter().filter_map(|value| value.as_ref())
+    }
+}
+
+impl<T, const CAP: usize> Default for PoolSlotMap<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_slot_map() {
+        let mut map = PoolSlotMap::<u32, 4>::new();
+        let keys: Vec<_> = (0..6).map(|i| map.insert(i)).collect();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.get(keys[4]), Some(&4));
+        assert_eq!(map.remove(keys[1]), Some(1));
+        assert_eq!(map.remove(keys[1]), None);
+        assert!(!map.contains_key(keys[1]));
+        let reused = map.insert(10);
+        assert_ne!(reused, keys[1]);
+        assert_eq!(map.get(keys[1]), None);
+        assert_eq!(map.get(reused), Some(&10));
+        *map.get_mut(keys[5]).unwrap() += 1;
+        assert_eq!(
+            map.values().copied().collect::<Vec<_>>(),
+            vec![0, 10, 2, 3, 4, 6]
+        );
+        assert_eq!(map.iter().nth(1), Some((reused, &10)));
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn test_pool_slot_map_releases_empty_blocks() {
+        let layout = SmallObjectPool::<Option<String>, 4>::alloc_layout();
+        let mut map = PoolSlotMap::<String, 4>::with_recycler(BlockRecycler::with_limit(layout, 1));
+        let keys: Vec<_> = (0..12).map(|i| map.insert(i.to_string())).collect();
+        assert_eq!(map.block_count(), 3);
+        // A block with a live value is kept, and so are the blocks before it.
+        for &key in &keys[4..11] {
+            map.remove(key);
+        }
+        assert_eq!(map.block_count(), 3);
+        map.remove(keys[11]);
+        assert_eq!((map.block_count(), map.recycler().len()), (1, 1));
+        for &key in &keys[4..] {
+            assert!(map.get(key).is_none());
+        }
+        // Released slots come back with new versions, from the recycled block.
+        let key = map.insert(String::from("back"));
+        assert_eq!((map.block_count(), map.recycler().len()), (2, 0));
+        assert_eq!(key.index, 4);
+        assert_ne!(key, keys[4]);
+        assert!(map.get(keys[4]).is_none());
+        assert_eq!(map.get(key).map(String::as_str), Some("back"));
+        for &key in &keys[..4] {
+            map.remove(key);
+        }
+        map.remove(key);
+        assert!(map.is_empty());
+        assert_eq!(map.block_count(), 1);
+        assert_eq!(map.into_recycler().len(), 1);
+    }
+
+    #[test]
+    fn test_pool_slot_map_retires_saturated_slots() {
+        let mut map = PoolSlotMap::<String, 4>::new();
+        let first = map.insert(String::from("first"));
+        // As if the slot had been reused 2^31 - 1 times.
+        map.versions[first.index] = u32::MAX;
+        let last = SlotKey {
+            index: first.index,
+            version: u32::MAX,
+        };
+        assert_eq!(map.remove(last).as_deref(), Some("first"));
+        let next = map.insert(String::from("next"));
+        assert_ne!(next.index, first.index);
+        assert!(map.get(first).is_none() && map.get(last).is_none());
+        assert_eq!(map.iter().map(|(key, _)| key).collect::<Vec<_>>(), [next]);
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -1,19 +1,168 @@
-use std::ptr::NonNull;
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    cell::{Cell, UnsafeCell},
+    marker::PhantomData,
+    mem::needs_drop,
+    ops::{Deref, DerefMut, Index, IndexMut, RangeBounds},
+    ptr::{slice_from_raw_parts_mut, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
+#[cfg(feature = "track-lifetimes")]
+use crate::lifetimes::{LifetimeReport, LifetimeTracker};
 use crate::{
     arraylike::ArrayLike,
-    linkedlist::{LinkedList, Node},
+    blockmeta::{BlockMeta, MinMax},
+    blocksource::{BlockMemory, BlockSource},
+    builder::PoolBuilder,
+    growth::{Fixed, Growable, Growth},
+    handle::PoolHandle,
+    index::{
+        block_index, slot_index, slot_to_usize, to_usize, BlockIndex, IndexOverflow, SlotIndex,
+    },
+    policy::{self, ensure, Violation},
     ptrbased::PtrBased,
+    relocate::OnRelocate,
+    tail::Tail,
 };
 
-pub struct SmallObjectPool<T, const CAP: usize> {
-    data: LinkedList<ArrayLike<T, CAP>>,
-    current_block: NonNull<Node<ArrayLike<T, CAP>>>,
-    last_block: NonNull<Node<ArrayLike<T, CAP>>>,
-    next_space: NonNull<T>,
-    last_space: NonNull<T>,
-    marked_block: NonNull<Node<ArrayLike<T, CAP>>>,
-    marked_space: NonNull<T>,
+pub(crate) type BlockPtr<T, const CAP: usize> = NonNull<ArrayLike<T, CAP>>;
+
+/// # SmallObjectPool
+/// A table of fixed-size blocks, indexed by block index. Each block is a
+/// separate allocation that never moves, so growing the table keeps pointers
+/// to elements valid while block lookup and iteration stay cache-friendly.
+/// The cursor lives in cells so that `alloc` can hand out references through
+/// a shared borrow. Elements are dropped when they are rewound, discarded or
+/// when the pool is dropped.
+pub struct SmallObjectPool<T, const CAP: usize, M = (), G = Growable> {
+    blocks: UnsafeCell<Vec<BlockPtr<T, CAP>>>,
+    layouts: UnsafeCell<Vec<Layout>>,
+    metas: UnsafeCell<Vec<M>>,
+    sequences: UnsafeCell<Vec<u64>>,
+    next_sequence: Cell<u64>,
+    current_block: Cell<BlockPtr<T, CAP>>,
+    next_space: Cell<NonNull<T>>,
+    last_space: Cell<NonNull<T>>,
+    current_index: Cell<BlockIndex>,
+    /// Number of elements in the blocks before the cursor block, so that
+    /// `len` doesn't have to walk them.
+    filled: Cell<usize>,
+    head: BlockIndex,
+    marked: Position<T, CAP>,
+    high_water: usize,
+    discarded: u64,
+    max_blocks: Option<usize>,
+    #[cfg(feature = "track-lifetimes")]
+    lifetimes: LifetimeTracker,
+    id: usize,
+    /// Whether `push_handle` has been called, from which point on the slots'
+    /// generations are kept up to date.
+    handles_issued: bool,
+    _growth: PhantomData<G>,
+}
+
+// The pool owns its blocks and their elements, so it can move to another
+// thread with them. It isn't `Sync`: `alloc` moves the cursor through a
+// shared borrow.
+unsafe impl<T: Send, const CAP: usize, M: Send, G: Send> Send for SmallObjectPool<T, CAP, M, G> {}
+
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// # Position
+/// A position in the pool: the block it falls in, the index of that block
+/// and the slot the next push would write to. The block's serial number lets
+/// the pool recognize positions into blocks it has freed since.
+pub struct Position<T, const CAP: usize> {
+    pub(crate) block: BlockPtr<T, CAP>,
+    pub(crate) index: BlockIndex,
+    pub(crate) space: NonNull<T>,
+    pub(crate) serial: u64,
+}
+
+impl<T, const CAP: usize> Position<T, CAP> {
+    /// Position of `space` in `block`, the block with the given index.
+    fn new(block: BlockPtr<T, CAP>, index: BlockIndex, space: NonNull<T>) -> Self {
+        let serial = if block == NonNull::dangling() {
+            0
+        } else {
+            unsafe { ArrayLike::serial_of(block) }
+        };
+        Position {
+            block,
+            index,
+            space,
+            serial,
+        }
+    }
+
+    /// Index of the block the position falls in
+    pub fn block_index(&self) -> BlockIndex {
+        self.index
+    }
+
+    /// Index of the slot within its block
+    pub fn slot(&self) -> SlotIndex {
+        if self.is_unallocated() {
+            return 0;
+        }
+        let begin = unsafe { ArrayLike::slots(self.block) };
+        let offset = unsafe { self.space.offset_from(begin) } as usize;
+        slot_index(offset).expect("CAP is checked against SlotIndex when the pool is created")
+    }
+
+    /// Position of a pool that has not allocated its first block yet.
+    fn unallocated() -> Self {
+        Position {
+            block: NonNull::dangling(),
+            index: 0,
+            space: NonNull::dangling(),
+            serial: 0,
+        }
+    }
+
+    fn is_unallocated(&self) -> bool {
+        self.block == NonNull::dangling()
+    }
+}
+
+impl<T, const CAP: usize> Clone for Position<T, CAP> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, const CAP: usize> Copy for Position<T, CAP> {}
+
+impl<T, const CAP: usize> PartialEq for Position<T, CAP> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.space == other.space
+    }
+}
+
+impl<T, const CAP: usize> Eq for Position<T, CAP> {}
+
+impl<T, const CAP: usize> PartialOrd for Position<T, CAP> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, const CAP: usize> Ord for Position<T, CAP> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.index
+            .cmp(&other.index)
+            .then(self.space.cmp(&other.space))
+    }
+}
+
+impl<T, const CAP: usize> std::fmt::Debug for Position<T, CAP> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Position")
+            .field("index", &self.index)
+            .field("space", &self.space)
+            .finish()
+    }
 }
 
 impl<T> PtrBased for Vec<T> {
@@ -56,177 +205,2608 @@ impl<T> PtrBased for Vec<T> {
     }
 }
 
-impl<T: Clone + Copy, const CAP: usize> SmallObjectPool<T, CAP> {
+/// Helpers that don't need the pool's bounds, for use in `Drop`.
+impl<T, const CAP: usize, M, G> SmallObjectPool<T, CAP, M, G> {
+    /// Pointers to the blocks still held, from the head block on.
+    pub(crate) fn blocks(&self) -> &[BlockPtr<T, CAP>] {
+        unsafe { &*self.blocks.get() }
+    }
+
+    /// Layout blocks are allocated with: aligned to at least 16 bytes, so
+    /// that they can be donated to a `RawPool`.
+    pub(crate) fn alloc_layout() -> Layout {
+        Layout::new::<ArrayLike<T, CAP>>().align_to(16).unwrap()
+    }
+
+    /// Offset in `blocks` of the block with the given index.
+    fn resident(&self, index: BlockIndex) -> usize {
+        to_usize(index - self.head)
+    }
+
+    /// Current position of the cursor
+    pub fn position(&self) -> Position<T, CAP> {
+        Position::new(
+            self.current_block.get(),
+            self.current_index.get(),
+            self.next_space.get(),
+        )
+    }
+
+    /// Start and length of the used part of every block up to the cursor.
+    fn raw_chunks(&self) -> impl Iterator<Item = (NonNull<T>, usize)> + '_ {
+        let end = self.position();
+        let count = if end.is_unallocated() {
+            0
+        } else {
+            self.resident(end.index) + 1
+        };
+        // The table is looked up again for every block, as `alloc` can grow
+        // it, and move it, between two steps.
+        (0..count).map(move |offset| {
+            let block = self.blocks()[offset];
+            let begin = unsafe { ArrayLike::slots(block) };
+            let len = if offset + 1 == count {
+                unsafe { end.space.offset_from(begin) as usize }
+            } else {
+                unsafe { ArrayLike::len_of(block) }
+            };
+            (begin, len)
+        })
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> SmallObjectPool<T, CAP, M, G> {
+    /// Create an empty pool. The first block of a `Growable` pool is only
+    /// allocated on the first push; a `Fixed` pool allocates all of its blocks here.
     pub fn new() -> Self {
-        let mut data = LinkedList::new();
-        data.push_back(ArrayLike::new());
-        let mut sop = SmallObjectPool {
-            data: data,
-            current_block: NonNull::dangling(),
-            last_block: NonNull::dangling(),
-            next_space: NonNull::dangling(),
-            last_space: NonNull::dangling(),
-            marked_block: NonNull::dangling(),
-            marked_space: NonNull::dangling(),
+        const {
+            assert!(
+                CAP <= slot_to_usize(SlotIndex::MAX),
+                "CAP doesn't fit in the configured slot index type"
+            );
+            assert!(
+                !matches!(G::MAX_BLOCKS, Some(0)),
+                "a fixed pool needs at least one block"
+            );
         };
-        sop.init();
-        sop
+        let pool = SmallObjectPool {
+            blocks: UnsafeCell::new(Vec::new()),
+            layouts: UnsafeCell::new(Vec::new()),
+            metas: UnsafeCell::new(Vec::new()),
+            sequences: UnsafeCell::new(Vec::new()),
+            next_sequence: Cell::new(0),
+            current_block: Cell::new(NonNull::dangling()),
+            next_space: Cell::new(NonNull::dangling()),
+            last_space: Cell::new(NonNull::dangling()),
+            current_index: Cell::new(0),
+            filled: Cell::new(0),
+            head: 0,
+            marked: Position::unallocated(),
+            high_water: 0,
+            discarded: 0,
+            max_blocks: None,
+            #[cfg(feature = "track-lifetimes")]
+            lifetimes: LifetimeTracker::default(),
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            handles_issued: false,
+            _growth: PhantomData,
+        };
+        pool.preallocate(G::MAX_BLOCKS.unwrap_or(0));
+        pool
     }
 
-    /// Initialize the pool
-    fn init(&mut self) {
-        self.current_block = self.data.begin().unwrap();
-        self.marked_block = self.current_block;
-        self.last_block = self.data.end().unwrap();
-        unsafe {
-            self.next_space = self.current_block.as_ref().inner().begin().unwrap();
-            self.last_space = self.current_block.as_ref().inner().end().unwrap();
-            self.marked_space = self.next_space;
+    /// Allocate blocks until the pool holds `count`, entering the first one
+    /// if the pool had none.
+    fn preallocate(&self, count: usize) {
+        while self.blocks().len() < count {
+            if self.try_append_block().is_err() {
+                handle_alloc_error(Self::alloc_layout());
+            }
+        }
+        if self.position().is_unallocated() {
+            if let Some(&first) = self.blocks().first() {
+                self.enter_block(first, self.head);
+            }
         }
     }
 
-    /// Create a new block
-    fn new_block(&mut self) {
-        self.data.push_back(ArrayLike::new());
-        self.last_block = self.data.end().unwrap();
-        unsafe {
-            self.current_block = self.last_block;
-            self.next_space = self.current_block.as_ref().inner().begin().unwrap();
-            self.last_space = self.current_block.as_ref().inner().end().unwrap();
+    /// Create an empty pool with room for `capacity` elements, see `reserve`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut pool = Self::new();
+        pool.reserve(capacity);
+        pool
+    }
+
+    /// Create an empty pool with room for `capacity` elements, adopting free
+    /// blocks from `source`, e.g. a `BlockRecycler`, before allocating any.
+    pub fn with_capacity_from(capacity: usize, source: &mut impl BlockSource) -> Self {
+        let mut pool = Self::new();
+        while pool.capacity() < capacity {
+            let Some(block) = source.take_free_block() else {
+                break;
+            };
+            if let Err(block) = pool.add_free_block(block) {
+                // The block was just the source's, so it fits again.
+                let _ = source.add_free_block(block);
+                break;
+            }
         }
+        pool.reserve(capacity);
+        pool
     }
 
-    /// Move to the next block
-    fn next_block(&mut self) {
-        if self.current_block == self.last_block {
-            self.new_block();
+    /// Drop the elements and give every block to `into`, freeing the ones it
+    /// doesn't adopt. Returns the number of blocks adopted.
+    pub fn recycle(mut self, into: &mut impl BlockSource) -> usize {
+        self.rewind();
+        let mut count = 0;
+        let layouts = self.layouts.get_mut().drain(..);
+        for (block, layout) in self.blocks.get_mut().drain(..).zip(layouts) {
+            let block = unsafe {
+                free_bookkeeping(block);
+                BlockMemory::from_raw(block.cast(), layout)
+            };
+            count += usize::from(into.add_free_block(block).is_ok());
+        }
+        // Nothing is left for the pool's own drop to free.
+        self.current_block.set(NonNull::dangling());
+        count
+    }
+
+    /// Collect `iter` into a new pool, allocating the blocks for `len_hint`
+    /// elements before consuming it. If the hint is too low, the pool grows
+    /// as usual; if it is too high, the extra blocks are left free. Hints
+    /// beyond what a `Fixed` pool can hold are clamped to it.
+    pub fn from_iter_with_len(iter: impl IntoIterator<Item = T>, len_hint: usize) -> Self {
+        let mut pool = Self::new();
+        let len_hint = match pool.max_blocks() {
+            Some(max) => len_hint.min(max * CAP),
+            None => len_hint,
+        };
+        pool.reserve(len_hint);
+        pool.extend(iter);
+        pool
+    }
+
+    /// Allocate enough blocks for `additional` more pushes not to allocate.
+    /// Slice allocations may leave the tail of a block unused and need more.
+    ///
+    /// # Panics
+    /// If a fixed or bounded pool doesn't have room for `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        let free = if self.position().is_unallocated() {
+            0
         } else {
-            unsafe {
-                self.current_block = self.data.next(self.current_block).unwrap();
-                self.next_space = self.current_block.as_ref().inner().begin().unwrap();
-                self.last_space = self.current_block.as_ref().inner().end().unwrap();
+            let spare = self.blocks().len() - self.resident(self.current_index.get()) - 1;
+            let remaining =
+                unsafe { self.last_space.get().offset_from(self.next_space.get()) } as usize;
+            spare * CAP + remaining
+        };
+        let needed = additional.saturating_sub(free).div_ceil(CAP);
+        let count = self.blocks().len() + needed;
+        if let Some(max) = self.max_blocks() {
+            assert!(
+                count <= max,
+                "cannot reserve {additional} more elements in a pool fixed to {max} blocks"
+            );
+        }
+        self.preallocate(count);
+    }
+
+    /// Same pool with another growth policy.
+    fn with_growth<G2: Growth>(self) -> SmallObjectPool<T, CAP, M, G2> {
+        let pool = std::mem::ManuallyDrop::new(self);
+        // Every field is moved out exactly once and `pool` is never dropped.
+        unsafe {
+            SmallObjectPool {
+                blocks: std::ptr::read(&pool.blocks),
+                layouts: std::ptr::read(&pool.layouts),
+                metas: std::ptr::read(&pool.metas),
+                sequences: std::ptr::read(&pool.sequences),
+                next_sequence: std::ptr::read(&pool.next_sequence),
+                current_block: std::ptr::read(&pool.current_block),
+                next_space: std::ptr::read(&pool.next_space),
+                last_space: std::ptr::read(&pool.last_space),
+                current_index: std::ptr::read(&pool.current_index),
+                filled: std::ptr::read(&pool.filled),
+                head: pool.head,
+                marked: pool.marked,
+                high_water: pool.high_water,
+                discarded: pool.discarded,
+                max_blocks: pool.max_blocks,
+                #[cfg(feature = "track-lifetimes")]
+                lifetimes: std::ptr::read(&pool.lifetimes),
+                id: pool.id,
+                handles_issued: pool.handles_issued,
+                _growth: PhantomData,
             }
         }
     }
 
-    /// Move to the previous block
-    pub fn rewind(&mut self) {
-        self.current_block = self.data.begin().unwrap();
+    /// Turn the pool into one that never allocates again, allocating up to
+    /// `MAX_BLOCKS` blocks now. Hands the pool back if it already holds more.
+    #[allow(clippy::result_large_err)]
+    pub fn into_fixed<const MAX_BLOCKS: usize>(
+        self,
+    ) -> Result<SmallObjectPool<T, CAP, M, Fixed<MAX_BLOCKS>>, Self> {
+        if MAX_BLOCKS == 0 || self.block_count() > MAX_BLOCKS {
+            return Err(self);
+        }
+        self.preallocate(MAX_BLOCKS);
+        Ok(self.with_growth())
+    }
+
+    /// Turn the pool into one that allocates blocks as needed, lifting the
+    /// bound of a `bounded` pool.
+    pub fn into_growable(mut self) -> SmallObjectPool<T, CAP, M, Growable> {
+        self.max_blocks = None;
+        self.with_growth()
+    }
+
+    /// Start configuring a pool.
+    pub fn builder() -> PoolBuilder<T, CAP, M> {
+        PoolBuilder::new()
+    }
+
+    /// Allocate the first block ahead of the first push.
+    pub(crate) fn allocate_first_block(&mut self) {
+        if self.blocks().is_empty() {
+            self.next_block();
+            self.mark();
+        }
+    }
+
+    /// Unique identifier of the pool
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Number of blocks held by the pool
+    pub fn block_count(&self) -> usize {
+        self.blocks().len()
+    }
+
+    /// Number of elements between the start of the pool and the cursor.
+    pub fn len(&self) -> usize {
+        if self.position().is_unallocated() {
+            return 0;
+        }
+        let begin = unsafe { ArrayLike::slots(self.current_block.get()) };
+        self.filled.get() + unsafe { self.next_space.get().offset_from(begin) } as usize
+    }
+
+    /// Number of elements in the resident blocks in `range`, not counting
+    /// the cursor block's own.
+    fn filled_in(&self, range: std::ops::Range<usize>) -> usize {
+        self.blocks()[range]
+            .iter()
+            .map(|&block| unsafe { ArrayLike::len_of(block) })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position() == self.start()
+    }
+
+    /// Number of slots in the blocks held by the pool, used or not.
+    pub fn capacity(&self) -> usize {
+        self.block_count() * CAP
+    }
+
+    /// Bytes allocated for the blocks held by the pool.
+    pub fn allocated_bytes(&self) -> usize {
+        unsafe { &*self.layouts.get() }
+            .iter()
+            .map(Layout::size)
+            .sum()
+    }
+
+    /// Largest `len` the pool has reached since it was created.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water.max(self.len())
+    }
+
+    /// Remember the current length before an operation that shrinks it.
+    fn update_high_water(&mut self) {
+        self.high_water = self.high_water_mark();
+    }
+
+    /// How long the elements lived between being pushed and being rewound or
+    /// discarded, timed per block.
+    #[cfg(feature = "track-lifetimes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "track-lifetimes")))]
+    pub fn lifetime_report(&self) -> LifetimeReport {
+        self.lifetimes.report()
+    }
+
+    /// Check the pool's internal invariants. A broken one is a violation
+    /// under the crate's `Policy`.
+    pub fn validate(&self) -> Result<(), Violation> {
+        let count = self.block_count();
+        ensure!(
+            unsafe { (*self.metas.get()).len() } == count,
+            Violation::BrokenInvariant,
+            "one meta per block"
+        );
+        if count == 0 {
+            ensure!(
+                self.position().is_unallocated(),
+                Violation::BrokenInvariant,
+                "cursor without blocks"
+            );
+            return Ok(());
+        }
+        let mut found_cursor = false;
+        let mut found_mark = self.marked.is_unallocated();
+        for (index, &current) in self.blocks().iter().enumerate() {
+            let inner = unsafe { current.as_ref() };
+            ensure!(
+                inner.len() <= CAP,
+                Violation::BrokenInvariant,
+                "block {index} is overfilled"
+            );
+            for position in [self.position(), self.marked] {
+                if position.block != current {
+                    continue;
+                }
+                ensure!(
+                    to_usize(position.index) == to_usize(self.head) + index,
+                    Violation::BrokenInvariant,
+                    "position index"
+                );
+                ensure!(
+                    inner.begin().unwrap() <= position.space
+                        && position.space <= inner.end().unwrap(),
+                    Violation::BrokenInvariant,
+                    "position outside of its block"
+                );
+            }
+            found_cursor |= self.current_block.get() == current;
+            found_mark |= self.marked.block == current;
+            if self.current_block.get() == current {
+                ensure!(
+                    self.last_space.get() == inner.end().unwrap(),
+                    Violation::BrokenInvariant,
+                    "cursor block end"
+                );
+            }
+        }
+        ensure!(
+            found_cursor,
+            Violation::BrokenInvariant,
+            "cursor block is not in the table"
+        );
+        ensure!(
+            self.len() == self.raw_chunks().map(|(_, len)| len).sum::<usize>(),
+            Violation::BrokenInvariant,
+            "the length is out of sync with the blocks"
+        );
+        ensure!(
+            found_mark,
+            Violation::BrokenInvariant,
+            "marked block is not in the table"
+        );
+        Ok(())
+    }
+
+    /// Allocate an empty block on the heap.
+    fn try_alloc_block() -> Result<BlockPtr<T, CAP>, AllocFailed> {
+        #[cfg(any(test, feature = "failing-alloc"))]
+        if crate::failing_alloc::should_fail() {
+            return Err(AllocFailed);
+        }
+        let layout = Self::alloc_layout();
+        let block =
+            NonNull::new(unsafe { alloc(layout) } as *mut ArrayLike<T, CAP>).ok_or(AllocFailed)?;
+        unsafe { block.as_ptr().write(ArrayLike::new()) };
+        Ok(block)
+    }
+
+    /// Whether the growth policy forbids allocating another block.
+    fn is_at_max_blocks(&self) -> bool {
+        self.max_blocks()
+            .is_some_and(|max| self.blocks().len() >= max)
+    }
+
+    /// Most blocks the pool may hold, set by its growth policy or by
+    /// `bounded`, or `None` if it grows without bound.
+    pub fn max_blocks(&self) -> Option<usize> {
+        match (G::MAX_BLOCKS, self.max_blocks) {
+            (Some(fixed), Some(bound)) => Some(fixed.min(bound)),
+            (fixed, bound) => fixed.or(bound),
+        }
+    }
+
+    /// Allocate a block at the end of the table, without moving the cursor.
+    fn try_append_block(&self) -> Result<(BlockPtr<T, CAP>, BlockIndex), AllocFailed> {
+        if self.is_at_max_blocks() {
+            return Err(AllocFailed);
+        }
+        // Running out of block indices fails like running out of memory.
+        let index = self.next_block_index().map_err(|_| AllocFailed)?;
+        let block = Self::try_alloc_block()?;
+        // No reference into the table is alive while a block is being appended.
         unsafe {
-            self.next_space = self.current_block.as_ref().inner().begin().unwrap();
-            self.last_space = self.current_block.as_ref().inner().end().unwrap();
+            (*self.blocks.get()).push(block);
+            (*self.layouts.get()).push(Self::alloc_layout());
+            (*self.metas.get()).push(M::default());
         }
+        Ok((block, index))
     }
 
-    /// Mark the current block and space
-    pub fn mark(&mut self) {
-        self.marked_block = self.current_block;
-        self.marked_space = self.next_space;
+    /// Index the next block appended to the table would get.
+    fn next_block_index(&self) -> Result<BlockIndex, IndexOverflow> {
+        let len = self.blocks().len();
+        block_index(len)
+            .ok()
+            .and_then(|len| self.head.checked_add(len))
+            .ok_or(IndexOverflow {
+                value: to_usize(self.head).saturating_add(len),
+                max: to_usize(BlockIndex::MAX),
+            })
     }
 
-    /// Rewind to the marked block and space
-    pub fn push(&mut self, value: T) {
+    /// Create a new block
+    fn try_new_block(&self) -> Result<(), AllocFailed> {
+        let (block, index) = self.try_append_block()?;
+        self.enter_block(block, index);
+        Ok(())
+    }
+
+    /// Move the cursor to the start of the given block
+    fn enter_block(&self, block: BlockPtr<T, CAP>, index: BlockIndex) {
+        #[cfg(feature = "track-lifetimes")]
+        self.lifetimes.entered(index);
+        self.current_block.set(block);
+        self.current_index.set(index);
         unsafe {
-            if self.next_space == self.last_space {
-                self.next_block();
+            self.next_space.set(ArrayLike::slots(block));
+            self.last_space.set(ArrayLike::slots(block).add(CAP));
+        }
+    }
+
+    /// Move to the next block
+    fn next_block(&self) {
+        if self.try_next_block().is_err() {
+            if self.is_at_max_blocks() {
+                policy::fail(format_args!(
+                    "{}: it is fixed to {} blocks",
+                    Violation::PoolFull,
+                    self.block_count()
+                ));
             }
-            self.next_space.as_ptr().write(value);
-            self.next_space = self
-                .current_block
-                .as_ref()
-                .inner()
-                .next(self.next_space)
-                .unwrap();
+            if let Err(error) = self.next_block_index() {
+                panic!("cannot allocate another block: {error}");
+            }
+            handle_alloc_error(Self::alloc_layout());
+        }
+    }
+
+    /// Move to the next block, failing if a new one is needed and can't be allocated
+    fn try_next_block(&self) -> Result<(), AllocFailed> {
+        let mut left = 0;
+        if !self.blocks().is_empty() {
+            let block = self.current_block.get();
+            // Record how far the block was filled so iteration can skip unused tail slots.
+            unsafe {
+                left = self.next_space.get().offset_from(ArrayLike::slots(block)) as usize;
+                ArrayLike::set_len_of(block, left);
+            }
+        }
+        let next = self.resident(self.current_index.get()) + 1;
+        if next >= self.blocks().len() {
+            self.try_new_block()?;
+        } else {
+            self.enter_block(self.blocks()[next], self.current_index.get() + 1);
+            self.reset_meta();
         }
+        self.filled.set(self.filled.get() + left);
+        Ok(())
     }
 
-    pub unsafe fn push_to_ptr(&mut self, value: T) -> NonNull<T> {
-        if self.next_space == self.last_space {
+    /// Take `n` contiguous free slots, moving to the next block if the
+    /// current one doesn't have enough room left.
+    fn bump_n(&self, n: usize) -> NonNull<T> {
+        assert!(
+            n <= CAP,
+            "cannot allocate {n} contiguous slots in blocks of {CAP}"
+        );
+        let remaining =
+            unsafe { self.last_space.get().offset_from(self.next_space.get()) } as usize;
+        if remaining < n {
             self.next_block();
         }
-        self.next_space.as_ptr().write(value);
-        let ptr = self.next_space;
-        self.next_space = self
-            .current_block
-            .as_ref()
-            .inner()
-            .next(self.next_space)
-            .unwrap();
+        let ptr = self.next_space.get();
+        self.next_space.set(unsafe { ptr.add(n) });
+        self.sequence(ptr, n);
         ptr
     }
 
-    pub unsafe fn emplace_back(&mut self) -> NonNull<T> {
-        if self.next_space == self.last_space {
+    /// Take the next free slot, moving to the next block if the current one is full.
+    fn bump(&self) -> NonNull<T> {
+        if self.next_space.get() == self.last_space.get() {
             self.next_block();
         }
-        let ptr = self.next_space;
-        self.next_space = self
-            .current_block
-            .as_ref()
-            .inner()
-            .next(self.next_space)
-            .unwrap();
+        let ptr = self.next_space.get();
+        self.next_space.set(unsafe { ptr.add(1) });
+        self.sequence(ptr, 1);
         ptr
     }
 
-    pub unsafe fn emplace_back_multi<const N: usize>(&mut self) -> NonNull<T> {
-        if self
-            .current_block
-            .as_ref()
-            .inner()
-            .distance(self.next_space, self.last_space)
-            < N
-        {
-            self.next_block();
+    /// Flat index of a slot of the cursor block among the slots of the
+    /// blocks still held, counting `CAP` slots per block.
+    fn flat_index(&self, ptr: NonNull<T>) -> usize {
+        let begin = unsafe { ArrayLike::slots(self.current_block.get()) };
+        self.resident(self.current_index.get()) * CAP + unsafe { ptr.offset_from(begin) } as usize
+    }
+
+    /// Give the `n` slots starting at `ptr` the next sequence numbers, once
+    /// a relocation has made them necessary.
+    fn sequence(&self, ptr: NonNull<T>, n: usize) {
+        // Nothing else borrows the sequences while a slot is being taken.
+        let sequences = unsafe { &mut *self.sequences.get() };
+        if sequences.is_empty() {
+            return;
+        }
+        let first = self.flat_index(ptr);
+        if sequences.len() < first + n {
+            sequences.resize(first + n, 0);
         }
+        for sequence in &mut sequences[first..first + n] {
+            *sequence = self.next_sequence.get();
+            self.next_sequence.set(self.next_sequence.get() + 1);
+        }
+    }
 
-        let ptr = self.next_space;
-        self.next_space = self.next_space.add(N);
-        ptr
+    /// Position of the first slot of the pool
+    pub fn start(&self) -> Position<T, CAP> {
+        match self.blocks().first() {
+            Some(&block) => Position::new(block, self.head, unsafe { ArrayLike::slots(block) }),
+            None => Position::unallocated(),
+        }
     }
-}
 
-impl<T, const CAP: usize> Drop for SmallObjectPool<T, CAP> {
-    fn drop(&mut self) {
-        let mut current = self.data.begin();
-        while let Some(block) = current {
-            unsafe {
-                block.drop_in_place();
+    /// Map positions taken before the first block existed, or in blocks
+    /// discarded since, to the start of the pool. Positions in blocks freed
+    /// since were past the cursor when their block was freed, so they map to
+    /// the cursor.
+    pub(crate) fn normalize(&self, position: Position<T, CAP>) -> Position<T, CAP> {
+        if position.is_unallocated() || position.index < self.head {
+            self.start()
+        } else if self.is_stale(position) {
+            self.position()
+        } else {
+            position
+        }
+    }
+
+    /// Whether `position` is in a block past the start of the pool that the
+    /// pool no longer holds, e.g. one freed by `shrink_to_fit`, even if a
+    /// new block has taken its place since.
+    pub(crate) fn is_stale(&self, position: Position<T, CAP>) -> bool {
+        if position.is_unallocated() || position.index < self.head {
+            return false;
+        }
+        match self.blocks().get(self.resident(position.index)) {
+            Some(&block) => position.serial != unsafe { ArrayLike::serial_of(block) },
+            None => true,
+        }
+    }
+
+    /// Move the cursor back to the given position, dropping the elements
+    /// after it. Elements past the cursor have been dropped already, so for
+    /// types with a destructor the cursor never moves forward.
+    pub(crate) fn rewind_to(&mut self, position: Position<T, CAP>) {
+        let end = self.position();
+        let mut position = self.normalize(position);
+        if position.is_unallocated() {
+            return;
+        }
+        if needs_drop::<T>() {
+            position = position.min(end);
+        }
+        #[cfg(feature = "track-lifetimes")]
+        if position < end {
+            self.lifetimes.released(position.index, end.index);
+        }
+        self.move_cursor(position);
+        if needs_drop::<T>() {
+            for ptr in self.raw_between(position, end) {
+                unsafe { ptr.as_ptr().drop_in_place() };
             }
-            let next = self.data.next(block);
-            match next {
-                Some(next_block) => {
-                    current = Some(next_block);
-                }
-                None => {
-                    break;
-                }
+        }
+    }
+
+    /// Move the cursor to the given allocated position, leaving the elements
+    /// after it as they are.
+    fn move_cursor(&mut self, position: Position<T, CAP>) {
+        self.update_high_water();
+        self.forget_reserved(position);
+        self.invalidate_handles(position);
+        if position == self.start() {
+            // Nothing is left to reorder.
+            self.sequences.get_mut().clear();
+        }
+        let (from, to) = (
+            self.resident(self.current_index.get()),
+            self.resident(position.index),
+        );
+        let filled = self.filled.get();
+        self.filled.set(if to < from {
+            filled - self.filled_in(to..from)
+        } else {
+            filled + self.filled_in(from..to)
+        });
+        self.enter_block(position.block, position.index);
+        if position.space != self.next_space.get() {
+            self.next_space.set(position.space);
+        } else {
+            self.reset_meta();
+        }
+    }
+
+    /// Drop the reservations of the slots from `position` up to the cursor.
+    fn forget_reserved(&mut self, position: Position<T, CAP>) {
+        let from = self.resident(position.index);
+        let to = self.resident(self.current_index.get()).max(from);
+        for (offset, block) in self.blocks.get_mut()[from..=to].iter_mut().enumerate() {
+            let slot = if offset == 0 {
+                slot_to_usize(position.slot())
+            } else {
+                0
+            };
+            unsafe { block.as_mut().forget_reserved_from(slot) };
+        }
+    }
+
+    /// Start a new generation for the slots from `position` up to the
+    /// cursor, so that handles to their elements no longer resolve.
+    fn invalidate_handles(&mut self, position: Position<T, CAP>) {
+        let end = self.position();
+        if !self.handles_issued || position >= end {
+            return;
+        }
+        let from = self.resident(position.index);
+        let to = self.resident(end.index);
+        let (first, last) = (slot_to_usize(position.slot()), slot_to_usize(end.slot()));
+        for (block, offset) in self.blocks.get_mut()[from..=to].iter_mut().zip(from..) {
+            let begin = if offset == from { first } else { 0 };
+            let end = if offset == to { last } else { CAP };
+            unsafe { block.as_mut().bump_generations(begin..end) };
+        }
+    }
+
+    /// Free every block that lies entirely before `position`, for pools
+    /// that only need to keep a sliding window of recent elements. Elements
+    /// before `position` in its own block are kept. Positions and indices into
+    /// the freed blocks resolve to the new start of the pool. Returns the
+    /// number of blocks freed.
+    pub fn discard_before(&mut self, position: Position<T, CAP>) -> usize {
+        let position = self.normalize(position).min(self.position());
+        if position.is_unallocated() {
+            return 0;
+        }
+        self.update_high_water();
+        let count = self.resident(position.index);
+        let freed = self.filled_in(0..count);
+        self.discarded += freed as u64;
+        self.filled.set(self.filled.get() - freed);
+        #[cfg(feature = "track-lifetimes")]
+        if count > 0 {
+            self.lifetimes.released(self.head, position.index - 1);
+        }
+        if needs_drop::<T>() {
+            for (begin, len) in self.raw_chunks().take(count) {
+                unsafe { slice_from_raw_parts_mut(begin.as_ptr(), len).drop_in_place() };
             }
         }
+        let layouts = self.layouts.get_mut().drain(..count);
+        for (block, layout) in self.blocks.get_mut().drain(..count).zip(layouts) {
+            unsafe { free_block(block, layout) };
+        }
+        self.metas.get_mut().drain(..count);
+        let sequences = self.sequences.get_mut();
+        sequences.drain(..sequences.len().min(count * CAP));
+        self.head = position.index;
+        self.marked = self.normalize(self.marked);
+        count
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Rewind to the first block
+    pub fn rewind(&mut self) {
+        self.rewind_to(self.start());
+    }
 
-    #[test]
-    fn test_small_object_pool() {
-        let mut sop = SmallObjectPool::<u32, 4>::new();
-        for i in 0..8 {
-            sop.push(i);
+    /// Mark the current block and space
+    pub fn mark(&mut self) {
+        self.marked = self.position();
+    }
+
+    /// Rewind to the marked block and space
+    pub fn rewind_to_mark(&mut self) {
+        self.rewind_to(self.marked);
+    }
+
+    /// Enter a scope: the current position is saved and restored when the
+    /// returned guard is dropped, even while unwinding from a panic.
+    pub fn enter_scope(&mut self) -> ScopeGuard<'_, T, CAP, M, G> {
+        ScopeGuard {
+            position: self.position(),
+            pool: self,
         }
     }
 
-    #[test]
-    fn test_small_object_pool_rewind() {
-        let mut sop = SmallObjectPool::<u32, 4>::new();
-        for i in 0..8 {
-            sop.push(i);
+    /// Position saved by the last `mark`
+    pub fn marked(&self) -> Position<T, CAP> {
+        self.marked
+    }
+
+    /// Walk the slots between two positions of this pool.
+    pub(crate) fn raw_between(
+        &self,
+        from: Position<T, CAP>,
+        to: Position<T, CAP>,
+    ) -> RawIter<T, CAP> {
+        RawIter {
+            blocks: unsafe { NonNull::new_unchecked(self.blocks.get()) },
+            head: self.head,
+            position: self.normalize(from),
+            end: self.normalize(to),
         }
-        sop.rewind();
-        for i in 0..8 {
-            sop.push(i);
+    }
+
+    /// Iterate over the elements between two positions of this pool.
+    pub(crate) fn iter_between(
+        &self,
+        from: Position<T, CAP>,
+        to: Position<T, CAP>,
+    ) -> Iter<'_, T, CAP> {
+        Iter {
+            raw: self.raw_between(from, to),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Mutably iterate over the elements between two positions of this pool.
+    pub(crate) fn iter_mut_between(
+        &mut self,
+        from: Position<T, CAP>,
+        to: Position<T, CAP>,
+    ) -> IterMut<'_, T, CAP> {
+        IterMut {
+            raw: self.raw_between(from, to),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Position of the live slot `ptr` points to, or `None` if it doesn't
+    /// point into the part of the pool written since the start.
+    pub fn locate(&self, ptr: NonNull<T>) -> Option<Position<T, CAP>> {
+        let end = self.position();
+        if end.is_unallocated() {
+            return None;
+        }
+        let resident = &self.blocks()[..=self.resident(end.index)];
+        for (index, &current) in (self.head..).zip(resident) {
+            let begin = unsafe { ArrayLike::slots(current) };
+            let last = if index == end.index {
+                end.space
+            } else {
+                filled_end(current)
+            };
+            if begin <= ptr && ptr < last {
+                return Some(Position::new(current, index, ptr));
+            }
+        }
+        None
+    }
+
+    /// Whether `ptr` points to a live slot of the pool.
+    pub fn contains(&self, ptr: NonNull<T>) -> bool {
+        self.locate(ptr).is_some()
+    }
+
+    /// Whether `ptr` points to a live slot of the pool that has been written,
+    /// i.e. not reserved with `emplace_back` and left uncommitted.
+    pub(crate) fn contains_initialized(&self, ptr: NonNull<T>) -> bool {
+        self.locate(ptr).is_some_and(|position| unsafe {
+            let block = position.block.as_ref();
+            let slot = slot_to_usize(position.slot());
+            block.reserved_before(slot + 1) == block.reserved_before(slot)
+        })
+    }
+
+    /// Pointer to the live slot at `index`, counting `CAP` slots per block.
+    fn slot_ptr(&self, index: usize) -> Option<NonNull<T>> {
+        let block = index / CAP;
+        let end = self.position();
+        if end.is_unallocated() || block < to_usize(self.head) || block > to_usize(end.index) {
+            return None;
+        }
+        let current = self.blocks()[block - to_usize(self.head)];
+        let ptr = unsafe { ArrayLike::slots(current).add(index % CAP) };
+        let last = if block == to_usize(end.index) {
+            end.space
+        } else {
+            filled_end(current)
+        };
+        (ptr < last).then_some(ptr)
+    }
+
+    /// Element in slot `index % CAP` of block `index / CAP`, in constant time.
+    ///
+    /// Indices count slots, so they match push order as long as no slice
+    /// allocation left the tail of a block unused. Returns `None` for slots
+    /// past the cursor, left unused or in discarded blocks.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.slot_ptr(index).map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Mutable access to the element at `index`, see `get`.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.slot_ptr(index)
+            .map(|ptr| unsafe { &mut *ptr.as_ptr() })
+    }
+
+    /// Remove the element at `index` and return it, moving the last element
+    /// into its place.
+    ///
+    /// # Panics
+    /// If there is no live element at `index`.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.swap_remove_with(index, None)
+    }
+
+    /// `swap_remove`, telling `on_relocate` when the last element moves.
+    ///
+    /// # Panics
+    /// If there is no live element at `index`.
+    pub fn swap_remove_notify(&mut self, index: usize, on_relocate: &mut impl OnRelocate<T>) -> T {
+        self.swap_remove_with(index, Some(on_relocate))
+    }
+
+    /// `swap_remove`, with handles to the moved element only made when
+    /// someone listens, as they make every later rewind bump generations.
+    fn swap_remove_with(&mut self, index: usize, on_relocate: Option<&mut dyn OnRelocate<T>>) -> T {
+        let ptr = self
+            .slot_ptr(index)
+            .unwrap_or_else(|| panic!("no live element at index {index}"));
+        let removed = unsafe { ptr.as_ptr().read() };
+        let last = self
+            .raw_between(self.start(), self.position())
+            .next_back()
+            .unwrap();
+        let last = self.locate(last).unwrap();
+        if last.space != ptr {
+            let last_index = to_usize(last.index) * CAP + slot_to_usize(last.slot());
+            let discarded = to_usize(self.head) * CAP;
+            unsafe {
+                ptr.as_ptr().write(last.space.as_ptr().read());
+                (&mut *self.metas.get())[index / CAP - to_usize(self.head)].record(&*ptr.as_ptr());
+            }
+            let sequences = self.start_sequences();
+            sequences[index - discarded] = sequences[last_index - discarded];
+            let old = on_relocate.is_some().then(|| {
+                self.handles_issued = true;
+                self.handle_at(last)
+            });
+            if self.handles_issued {
+                let block = self.blocks.get_mut()[index / CAP - to_usize(self.head)];
+                unsafe { (*block.as_ptr()).bump_generations(index % CAP..index % CAP + 1) };
+            }
+            if let (Some(on_relocate), Some(old)) = (on_relocate, old) {
+                let new = self.handle_at(self.locate(ptr).unwrap());
+                on_relocate.relocated(old, new);
+            }
         }
+        // The last element has been moved out, so it must not be dropped.
+        self.move_cursor(last);
+        removed
+    }
+
+    /// Remove the last element and return it, or `None` if the pool is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let mut last = self.position();
+        if last.is_unallocated() {
+            return None;
+        }
+        // Step back over blocks left empty until one holds an element.
+        while last.space == unsafe { ArrayLike::slots(last.block) } {
+            if last.index == self.head {
+                return None;
+            }
+            let block = self.blocks()[self.resident(last.index - 1)];
+            last = Position::new(block, last.index - 1, filled_end(block));
+        }
+        last.space = unsafe { last.space.sub(1) };
+        let value = unsafe { last.space.as_ptr().read() };
+        // The last element has been moved out, so it must not be dropped.
+        self.move_cursor(last);
+        Some(value)
+    }
+
+    /// Sequence numbers of every slot of the blocks still held, numbering the
+    /// slots in storage order the first time they are needed.
+    fn start_sequences(&mut self) -> &mut Vec<u64> {
+        let slots = self.blocks().len() * CAP;
+        let sequences = self.sequences.get_mut();
+        if sequences.is_empty() {
+            sequences.extend(0..slots as u64);
+            self.next_sequence.set(slots as u64);
+        }
+        sequences
+    }
+
+    /// Iterate over the elements in the order they were pushed.
+    ///
+    /// This is the storage order of `iter` until `swap_remove` moves an
+    /// element; from then on every slot carries a sequence number and this
+    /// sorts the elements by it.
+    pub fn iter_insertion_order(&self) -> impl Iterator<Item = &T> {
+        let mut slots: Vec<(usize, NonNull<T>)> = self
+            .raw_chunks()
+            .enumerate()
+            .flat_map(|(block, (begin, len))| {
+                (0..len).map(move |slot| (block * CAP + slot, unsafe { begin.add(slot) }))
+            })
+            .collect();
+        let sequences = unsafe { &*self.sequences.get() };
+        if !sequences.is_empty() {
+            slots.sort_unstable_by_key(|(index, _)| sequences[*index]);
+        }
+        slots.into_iter().map(|(_, ptr)| unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Sequence number the next pushed element will get. Elements are
+    /// numbered from zero in insertion order, counting those freed by
+    /// `discard_before`; rewound elements give their numbers back.
+    pub fn next_seq(&self) -> u64 {
+        self.discarded + self.len() as u64
+    }
+
+    /// Append the elements of `other` numbered `since_seq` or later, in
+    /// sequence order, and return `other.next_seq()` to pass as `since_seq`
+    /// next time. A replica merging from a primary that only ever pushes and
+    /// discards ends up with the primary's log, whatever the merge schedule.
+    ///
+    /// # Panics
+    /// If `other` has already discarded elements numbered `since_seq` or later.
+    pub fn merge_append_only<M2: BlockMeta<T>, G2: Growth>(
+        &mut self,
+        other: &SmallObjectPool<T, CAP, M2, G2>,
+        since_seq: u64,
+    ) -> u64
+    where
+        T: Clone,
+    {
+        assert!(
+            since_seq >= other.discarded,
+            "elements from sequence {since_seq} on were discarded up to {}",
+            other.discarded
+        );
+        let skip = usize::try_from(since_seq - other.discarded).unwrap_or(usize::MAX);
+        self.extend(other.iter_insertion_order().skip(skip).cloned());
+        other.next_seq()
+    }
+
+    /// Iterate over the elements pushed since the start of the pool.
+    pub fn iter(&self) -> Iter<'_, T, CAP> {
+        self.iter_between(self.start(), self.position())
+    }
+
+    /// Mutably iterate over the elements pushed since the start of the pool.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, CAP> {
+        self.iter_mut_between(self.start(), self.position())
+    }
+
+    /// Subscribe to the elements pushed from now on.
+    pub fn subscribe(&self) -> Tail<T, CAP> {
+        Tail::new(self)
+    }
+
+    /// Run `f` inside a scope, rewinding to the entry position when it returns.
+    pub fn scope<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let mut guard = self.enter_scope();
+        f(&mut guard)
+    }
+
+    /// Reset the meta of the current block, which is about to be refilled from its start.
+    fn reset_meta(&self) {
+        let index = self.resident(self.current_index.get());
+        unsafe { (&mut *self.metas.get())[index] = M::default() };
+    }
+
+    /// Update the meta of the current block with a value just written to it.
+    fn record(&self, value: &T) {
+        let index = self.resident(self.current_index.get());
+        unsafe { (&mut *self.metas.get())[index].record(value) };
+    }
+
+    /// Iterate over the used part of every block up to the cursor, as slices.
+    pub fn chunks(&self) -> impl Iterator<Item = &[T]> {
+        self.raw_chunks()
+            .map(|(begin, len)| unsafe { std::slice::from_raw_parts(begin.as_ptr(), len) })
+    }
+
+    /// Mutably iterate over the used part of every block up to the cursor, as slices.
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut [T]> {
+        self.raw_chunks()
+            .map(|(begin, len)| unsafe { std::slice::from_raw_parts_mut(begin.as_ptr(), len) })
+    }
+
+    /// Set every element to `value`, a block at a time.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        for chunk in self.chunks_mut() {
+            chunk.fill(value.clone());
+        }
+    }
+
+    /// Set every element to zero with one `write_bytes` per block, which is
+    /// much faster than `fill` for large numeric pools.
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytemuck")))]
+    pub fn zero_all(&mut self)
+    where
+        T: bytemuck::Zeroable,
+    {
+        for (begin, len) in self.raw_chunks() {
+            unsafe { begin.as_ptr().write_bytes(0, len) };
+        }
+    }
+
+    /// Iterate over the used part of every block up to the cursor, together with its meta.
+    /// Takes `&mut self` so that no `alloc` can update a meta while it is borrowed.
+    pub fn blocks_with_meta(&mut self) -> impl Iterator<Item = (&[T], &M)> + '_ {
+        let metas = unsafe { &*self.metas.get() };
+        self.chunks().zip(metas)
+    }
+
+    /// Push a value at the end of the pool, handing it back if a new block
+    /// is needed and can't be allocated.
+    pub fn try_push(&mut self, value: T) -> Result<(), PoolFull<T>> {
+        if self.next_space.get() == self.last_space.get() && self.try_next_block().is_err() {
+            return Err(PoolFull(value));
+        }
+        self.push(value);
+        Ok(())
+    }
+
+    /// Push a value at the end of the pool
+    pub fn push(&mut self, value: T) {
+        let ptr = self.bump();
+        self.record(&value);
+        unsafe { ptr.as_ptr().write(value) };
+    }
+
+    /// Push every value of `values`, copying them a block segment at a time.
+    pub fn extend_from_slice(&mut self, values: &[T])
+    where
+        T: Copy,
+    {
+        let mut rest = values;
+        while !rest.is_empty() {
+            if self.next_space.get() == self.last_space.get() {
+                self.next_block();
+            }
+            let room = unsafe { self.last_space.get().offset_from(self.next_space.get()) } as usize;
+            let (segment, tail) = rest.split_at(room.min(rest.len()));
+            let ptr = self.bump_n(segment.len());
+            unsafe { std::ptr::copy_nonoverlapping(segment.as_ptr(), ptr.as_ptr(), segment.len()) };
+            segment.iter().for_each(|value| self.record(value));
+            rest = tail;
+        }
+    }
+
+    /// Push a value and return a handle to it, checked when resolved.
+    pub fn push_handle(&mut self, value: T) -> PoolHandle<T> {
+        self.handles_issued = true;
+        let ptr = self.bump();
+        self.record(&value);
+        unsafe { ptr.as_ptr().write(value) };
+        let position = Position::new(self.current_block.get(), self.current_index.get(), ptr);
+        self.handle_at(position)
+    }
+
+    /// Handle to the element at `position`, with the slot's current generation.
+    fn handle_at(&self, position: Position<T, CAP>) -> PoolHandle<T> {
+        let slot = position.slot();
+        let (serial, generation) = unsafe {
+            (
+                ArrayLike::serial_of(position.block),
+                position.block.as_ref().generation(slot_to_usize(slot)),
+            )
+        };
+        PoolHandle::new(self.id, position.index, slot, serial, generation)
+    }
+
+    /// Flat slot index of the element `handle` refers to, if it was pushed to this pool.
+    fn handle_index(&self, handle: &PoolHandle<T>) -> Option<usize> {
+        (handle.pool_id == self.id)
+            .then(|| to_usize(handle.index) * CAP + slot_to_usize(handle.slot))
+    }
+
+    /// Slot of the element `handle` refers to, if it is still live: the slot
+    /// is in the live part of the pool, in the block it was pushed to, and
+    /// hasn't been rewound or replaced since.
+    fn handle_slot(&self, index: usize, handle: &PoolHandle<T>) -> Option<NonNull<T>> {
+        let ptr = self.slot_ptr(index)?;
+        let block = self.blocks()[index / CAP - to_usize(self.head)];
+        let live = unsafe {
+            ArrayLike::serial_of(block) == handle.serial
+                && block.as_ref().generation(index % CAP) == handle.generation
+        };
+        live.then_some(ptr)
+    }
+
+    /// The element `handle` refers to, or `None` if it is no longer in the
+    /// live part of the pool or was pushed to another pool.
+    pub fn resolve(&self, handle: &PoolHandle<T>) -> Option<&T> {
+        let ptr = self.handle_slot(self.handle_index(handle)?, handle)?;
+        Some(unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Mutable access to the element `handle` refers to, see `resolve`.
+    pub fn resolve_mut(&mut self, handle: &PoolHandle<T>) -> Option<&mut T> {
+        let ptr = self.handle_slot(self.handle_index(handle)?, handle)?;
+        Some(unsafe { &mut *ptr.as_ptr() })
+    }
+
+    /// The element `handle` refers to, with a stale or foreign handle
+    /// treated as a violation under the crate's `Policy`.
+    #[track_caller]
+    pub fn get_checked(&self, handle: &PoolHandle<T>) -> Result<&T, Violation> {
+        let (_, ptr) = self.checked_slot(handle)?;
+        Ok(unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Mutable access to the element `handle` refers to, see `get_checked`.
+    #[track_caller]
+    pub fn get_checked_mut(&mut self, handle: &PoolHandle<T>) -> Result<&mut T, Violation> {
+        let (_, ptr) = self.checked_slot(handle)?;
+        Ok(unsafe { &mut *ptr.as_ptr() })
+    }
+
+    /// Flat index and slot of the live element `handle` refers to, with a
+    /// stale or foreign handle treated as a violation.
+    #[track_caller]
+    fn checked_slot(&self, handle: &PoolHandle<T>) -> Result<(usize, NonNull<T>), Violation> {
+        let index = self
+            .handle_index(handle)
+            .ok_or_else(|| policy::violate(Violation::ForeignHandle))?;
+        let ptr = self
+            .handle_slot(index, handle)
+            .ok_or_else(|| policy::violate(Violation::StaleHandle))?;
+        Ok((index, ptr))
+    }
+
+    /// Swap the elements `a` and `b` refer to in place, after checking both
+    /// handles as `get_checked` does. The elements keep their sequence
+    /// numbers, so `iter_insertion_order` still yields them in push order.
+    #[track_caller]
+    pub fn swap(&mut self, a: &PoolHandle<T>, b: &PoolHandle<T>) -> Result<(), Violation> {
+        let (a, a_ptr) = self.checked_slot(a)?;
+        let (b, b_ptr) = self.checked_slot(b)?;
+        if a == b {
+            return Ok(());
+        }
+        let head = to_usize(self.head);
+        unsafe {
+            std::ptr::swap_nonoverlapping(a_ptr.as_ptr(), b_ptr.as_ptr(), 1);
+            let metas = self.metas.get_mut();
+            metas[a / CAP - head].record(&*a_ptr.as_ptr());
+            metas[b / CAP - head].record(&*b_ptr.as_ptr());
+        }
+        self.start_sequences().swap(a - head * CAP, b - head * CAP);
+        Ok(())
+    }
+
+    /// Allocate a value in the pool and return a reference to it.
+    ///
+    /// Blocks never move, so the reference stays valid for as long as the
+    /// pool is borrowed; rewinding requires `&mut self` and therefore cannot
+    /// happen while it is alive. The reference is shared, as other readers
+    /// of the pool can reach the value too; use `Cell`s for values that
+    /// change after allocation.
+    pub fn alloc(&self, value: T) -> &T {
+        unsafe { self.alloc_ptr(value).as_ref() }
+    }
+
+    /// `alloc`, returning a pointer that may be written through for as long
+    /// as no reference to the value is alive.
+    pub(crate) fn alloc_ptr(&self, value: T) -> NonNull<T> {
+        let ptr = self.bump();
+        self.record(&value);
+        unsafe { ptr.as_ptr().write(value) };
+        ptr
+    }
+
+    /// Push a value and return a pointer to it.
+    ///
+    /// # Safety
+    /// The returned pointer is invalidated logically by any rewind past it.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) unsafe fn push_to_ptr(&mut self, value: T) -> NonNull<T> {
+        let ptr = self.bump();
+        self.record(&value);
+        ptr.as_ptr().write(value);
+        ptr
+    }
+
+    /// Number of free slots left in the current block.
+    pub fn free_in_block(&self) -> usize {
+        unsafe { self.last_space.get().offset_from(self.next_space.get()) as usize }
+    }
+
+    /// The free slots of the current block, to be written in place and then
+    /// committed with `commit`. Moves to the next block first if the current
+    /// one is full, so the slice is never empty.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) fn raw_slots_mut(&mut self) -> &mut [std::mem::MaybeUninit<T>] {
+        if self.free_in_block() == 0 {
+            self.next_block();
+        }
+        let begin = self.next_space.get().as_ptr() as *mut std::mem::MaybeUninit<T>;
+        unsafe { std::slice::from_raw_parts_mut(begin, self.free_in_block()) }
+    }
+
+    /// Move the cursor over the first `n` slots returned by `raw_slots_mut`,
+    /// recording them in the block meta.
+    ///
+    /// # Safety
+    /// The `n` slots must be initialized.
+    ///
+    /// # Panics
+    /// If `n` is larger than the number of free slots in the current block.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) unsafe fn commit(&mut self, n: usize) {
+        let free = self.free_in_block();
+        assert!(n <= free, "cannot commit {n} slots, {free} are free");
+        let ptr = self.next_space.get();
+        self.next_space.set(ptr.add(n));
+        self.sequence(ptr, n);
+        for i in 0..n {
+            self.record(&*ptr.add(i).as_ptr());
+        }
+    }
+
+    /// Reserve a slot and return a pointer to it, without initializing it.
+    /// The slot is not recorded in the block meta, and counts as uninitialized
+    /// until `commit_emplaced` is called for it.
+    ///
+    /// # Safety
+    /// The slot must be written before it is read, or dropped by a rewind
+    /// or by the pool itself.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) unsafe fn emplace_back(&mut self) -> NonNull<T> {
+        self.emplace_n(1)
+    }
+
+    /// Reserve `N` contiguous slots and return a pointer to the first one.
+    ///
+    /// # Safety
+    /// As for `emplace_back`, for each of the `N` slots.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) unsafe fn emplace_back_multi<const N: usize>(&mut self) -> NonNull<T> {
+        self.emplace_n(N)
+    }
+
+    /// Take `n` contiguous slots, recording them as uninitialized in their block.
+    #[cfg(any(test, feature = "raw"))]
+    fn emplace_n(&mut self, n: usize) -> NonNull<T> {
+        let ptr = self.bump_n(n);
+        let block = self.current_block.get();
+        unsafe {
+            let slot = ptr.offset_from(ArrayLike::slots(block)) as usize;
+            (*block.as_ptr()).reserve_uninit(slot, n);
+        }
+        ptr
+    }
+
+    /// Record that the `n` slots starting at `ptr`, reserved with
+    /// `emplace_back` or `emplace_back_multi`, have been written.
+    ///
+    /// # Safety
+    /// The `n` slots must be initialized.
+    ///
+    /// # Panics
+    /// If the slots are not live, reserved and uncommitted.
+    #[cfg(any(test, feature = "raw"))]
+    pub(crate) unsafe fn commit_emplaced(&mut self, ptr: NonNull<T>, n: usize) {
+        let position = self
+            .locate(ptr)
+            .expect("committed slots are not in the live part of the pool");
+        let committed = (*position.block.as_ptr()).commit_uninit(slot_to_usize(position.slot()), n);
+        assert!(committed, "committed slots were not reserved uninitialized");
+    }
+
+    /// Number of live slots reserved with `emplace_back` or
+    /// `emplace_back_multi` and not committed since.
+    pub fn uninitialized_len(&self) -> usize {
+        self.blocks()
+            .iter()
+            .zip(self.raw_chunks())
+            .map(|(block, (_, len))| unsafe { block.as_ref().reserved_before(len) })
+            .sum()
+    }
+
+    /// Check that every live slot has been written, e.g. before iterating
+    /// after filling reserved slots.
+    ///
+    /// # Panics
+    /// If a reserved slot hasn't been committed with `commit_emplaced`.
+    pub fn assert_fully_initialized(&self) {
+        let uninitialized = self.uninitialized_len();
+        assert_eq!(
+            uninitialized, 0,
+            "{uninitialized} reserved slots were never committed"
+        );
+    }
+
+    /// Allocate a copy of `values` in a single block and return it as a slice.
+    ///
+    /// # Panics
+    /// If `values` is longer than `CAP`.
+    pub fn alloc_slice_copy(&self, values: &[T]) -> &[T]
+    where
+        T: Copy,
+    {
+        unsafe {
+            let ptr = self.bump_n(values.len());
+            std::ptr::copy_nonoverlapping(values.as_ptr(), ptr.as_ptr(), values.len());
+            values.iter().for_each(|value| self.record(value));
+            std::slice::from_raw_parts(ptr.as_ptr(), values.len())
+        }
+    }
+
+    /// Allocate `len` contiguous values in a single block, initializing
+    /// slot `i` with `f(i)`, and return them as a slice.
+    ///
+    /// # Panics
+    /// If `len` is larger than `CAP`.
+    pub fn alloc_slice_fill_with(&self, len: usize, mut f: impl FnMut(usize) -> T) -> &[T] {
+        unsafe {
+            let ptr = self.bump_n(len);
+            for i in 0..len {
+                let value = f(i);
+                self.record(&value);
+                ptr.add(i).as_ptr().write(value);
+            }
+            std::slice::from_raw_parts(ptr.as_ptr(), len)
+        }
+    }
+}
+
+/// Failure to allocate a new block.
+struct AllocFailed;
+
+/// # PoolFull
+/// Returned with the value that couldn't be stored when the pool can't grow.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PoolFull<T>(pub T);
+
+impl<T> std::fmt::Display for PoolFull<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the pool can't allocate another block")
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for PoolFull<T> {}
+
+impl<T, const CAP: usize, M: BlockMeta<T>> SmallObjectPool<T, CAP, M, Growable> {
+    /// Create a pool that allocates `max_blocks` blocks now and never more,
+    /// like a `FixedPool` whose size is only known at run time. Once it is
+    /// full, `try_push` hands the value back in `Err(PoolFull(value))` rather
+    /// than calling the allocator, and `push` panics.
+    ///
+    /// # Panics
+    /// If `max_blocks` is zero.
+    pub fn bounded(max_blocks: usize) -> Self {
+        assert!(max_blocks > 0, "a bounded pool needs at least one block");
+        let mut pool = Self::new();
+        pool.max_blocks = Some(max_blocks);
+        pool.preallocate(max_blocks);
+        pool
+    }
+
+    /// Free the blocks past the cursor and the mark, returning how many
+    /// were freed. Only growable pools can shrink, since the blocks would
+    /// have to be allocated again to refill them; a `bounded` pool keeps its
+    /// blocks and frees none. Positions into the freed
+    /// blocks, e.g. of a `Tail`, resolve to the cursor from then on.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let mut count = 0;
+        while self.take_free_block().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Rewind to the first block and free all the others. The mark is
+    /// forgotten, as it would otherwise keep its block allocated.
+    pub fn rewind_and_free(&mut self) -> usize {
+        self.rewind();
+        self.marked = Position::unallocated();
+        self.shrink_to_fit()
+    }
+}
+
+impl<T: Copy + PartialOrd, const CAP: usize, G: Growth> SmallObjectPool<T, CAP, MinMax<T>, G> {
+    /// Iterate over the values in `range`, skipping every block whose min/max
+    /// summary shows it holds none of them.
+    pub fn filtered_scan<R: RangeBounds<T> + Clone>(
+        &mut self,
+        range: R,
+    ) -> impl Iterator<Item = &T> {
+        let block_range = range.clone();
+        self.blocks_with_meta()
+            .filter(move |(_, meta)| meta.overlaps(&block_range))
+            .flat_map(|(values, _)| values.iter())
+            .filter(move |value| range.contains(value))
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> Default for SmallObjectPool<T, CAP, M, G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> BlockSource
+    for SmallObjectPool<T, CAP, M, G>
+{
+    fn block_layout(&self) -> Layout {
+        Self::alloc_layout()
+    }
+
+    /// Give away the last block, unless the cursor or the mark is in it.
+    /// Fixed and bounded pools keep their blocks, as they would otherwise
+    /// have to allocate again to refill them.
+    fn take_free_block(&mut self) -> Option<BlockMemory> {
+        let position = self.position();
+        if position.is_unallocated() || self.max_blocks().is_some() {
+            return None;
+        }
+        let last = self.head + block_index(self.blocks().len() - 1).ok()?;
+        if last <= position.index || last <= self.normalize(self.marked).index {
+            return None;
+        }
+        let block = self.blocks.get_mut().pop()?;
+        let layout = self.layouts.get_mut().pop()?;
+        self.metas.get_mut().pop();
+        let slots = self.blocks().len() * CAP;
+        self.sequences.get_mut().truncate(slots);
+        unsafe {
+            free_bookkeeping(block);
+            Some(BlockMemory::from_raw(block.cast(), layout))
+        }
+    }
+
+    /// Adopt `block` after the last block. A pool that hasn't allocated its
+    /// first block yet starts writing to it.
+    fn add_free_block(&mut self, block: BlockMemory) -> Result<(), BlockMemory> {
+        if !block.fits(self.block_layout()) || self.is_at_max_blocks() {
+            return Err(block);
+        }
+        let (ptr, layout) = block.into_raw();
+        let block: BlockPtr<T, CAP> = ptr.cast();
+        unsafe { block.as_ptr().write(ArrayLike::new()) };
+        self.blocks.get_mut().push(block);
+        self.layouts.get_mut().push(layout);
+        self.metas.get_mut().push(M::default());
+        if self.position().is_unallocated() {
+            self.enter_block(block, self.head);
+        }
+        Ok(())
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> Extend<T> for SmallObjectPool<T, CAP, M, G> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| self.push(value));
+    }
+}
+
+impl<'a, T: Clone + Copy + 'a, const CAP: usize, M: BlockMeta<T>, G: Growth> Extend<&'a T>
+    for SmallObjectPool<T, CAP, M, G>
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> FromIterator<T>
+    for SmallObjectPool<T, CAP, M, G>
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut pool = Self::new();
+        pool.extend(iter);
+        pool
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> Index<usize>
+    for SmallObjectPool<T, CAP, M, G>
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index)
+            .unwrap_or_else(|| panic!("no live element at index {index}"))
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> IndexMut<usize>
+    for SmallObjectPool<T, CAP, M, G>
+{
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("no live element at index {index}"))
+    }
+}
+
+/// # ScopeGuard
+/// Restores the pool position saved by `SmallObjectPool::enter_scope` when dropped.
+pub struct ScopeGuard<'a, T, const CAP: usize, M: BlockMeta<T> = (), G: Growth = Growable> {
+    pool: &'a mut SmallObjectPool<T, CAP, M, G>,
+    position: Position<T, CAP>,
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> Deref for ScopeGuard<'_, T, CAP, M, G> {
+    type Target = SmallObjectPool<T, CAP, M, G>;
+
+    fn deref(&self) -> &Self::Target {
+        self.pool
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> DerefMut for ScopeGuard<'_, T, CAP, M, G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.pool
+    }
+}
+
+impl<T, const CAP: usize, M: BlockMeta<T>, G: Growth> Drop for ScopeGuard<'_, T, CAP, M, G> {
+    fn drop(&mut self) {
+        self.pool.rewind_to(self.position);
+    }
+}
+
+/// # RawIter
+/// Walks the slots of a pool between two positions, from either end.
+/// Holds a pointer to the pool's block table, so it must not outlive the pool.
+pub struct RawIter<T, const CAP: usize> {
+    blocks: NonNull<Vec<BlockPtr<T, CAP>>>,
+    head: BlockIndex,
+    position: Position<T, CAP>,
+    end: Position<T, CAP>,
+}
+
+impl<T, const CAP: usize> RawIter<T, CAP> {
+    /// Block with the given index, if the pool holds one.
+    fn block(&self, index: BlockIndex) -> Option<BlockPtr<T, CAP>> {
+        let offset = to_usize(index.checked_sub(self.head)?);
+        unsafe { self.blocks.as_ref().get(offset).copied() }
+    }
+}
+
+/// Drop a block and return its memory, allocated with `layout`.
+///
+/// # Safety
+/// Nothing may point into the block anymore.
+unsafe fn free_block<T, const CAP: usize>(block: BlockPtr<T, CAP>, layout: Layout) {
+    free_bookkeeping(block);
+    dealloc(block.as_ptr() as *mut u8, layout);
+}
+
+/// Drop a block's bookkeeping but not its elements, which the pool drops itself.
+unsafe fn free_bookkeeping<T, const CAP: usize>(block: BlockPtr<T, CAP>) {
+    (*block.as_ptr()).set_len(0);
+    block.drop_in_place();
+}
+
+/// Slot one past the last used one of a block.
+fn filled_end<T, const CAP: usize>(block: BlockPtr<T, CAP>) -> NonNull<T> {
+    unsafe { ArrayLike::slots(block).add(ArrayLike::len_of(block)) }
+}
+
+impl<T, const CAP: usize> Iterator for RawIter<T, CAP> {
+    type Item = NonNull<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.position >= self.end {
+                return None;
+            }
+            let block = self.position.block;
+            if self.position.index < self.end.index && self.position.space == filled_end(block) {
+                let index = self.position.index + 1;
+                let next = self.block(index)?;
+                self.position = Position::new(next, index, unsafe { ArrayLike::slots(next) });
+                continue;
+            }
+            let item = self.position.space;
+            self.position.space = unsafe { item.add(1) };
+            return Some(item);
+        }
+    }
+}
+
+impl<T, const CAP: usize> DoubleEndedIterator for RawIter<T, CAP> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.position >= self.end {
+                return None;
+            }
+            let block = self.end.block;
+            let begin = unsafe { ArrayLike::slots(block) };
+            if self.end.index > self.position.index && self.end.space == begin {
+                let index = self.end.index - 1;
+                let prev = self.block(index)?;
+                self.end = Position::new(prev, index, filled_end(prev));
+                continue;
+            }
+            self.end.space = unsafe { self.end.space.sub(1) };
+            return Some(self.end.space);
+        }
+    }
+}
+
+/// # Iter
+/// Iterator over the elements of a pool between two positions. It isn't
+/// `Send`, as the pool it borrows can still grow its block table through
+/// `alloc` while the iterator reads it.
+pub struct Iter<'a, T, const CAP: usize> {
+    raw: RawIter<T, CAP>,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T, const CAP: usize> Iterator for Iter<'a, T, CAP> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next().map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+}
+
+impl<T, const CAP: usize> DoubleEndedIterator for Iter<'_, T, CAP> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw.next_back().map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+}
+
+/// # IterMut
+/// Mutable iterator over the elements of a pool between two positions.
+pub struct IterMut<'a, T, const CAP: usize> {
+    raw: RawIter<T, CAP>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+// The iterator borrows the pool exclusively, so it hands out its elements
+// like a `slice::IterMut`.
+unsafe impl<T: Send, const CAP: usize> Send for IterMut<'_, T, CAP> {}
+unsafe impl<T: Sync, const CAP: usize> Sync for IterMut<'_, T, CAP> {}
+
+impl<'a, T, const CAP: usize> Iterator for IterMut<'a, T, CAP> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next().map(|ptr| unsafe { &mut *ptr.as_ptr() })
+    }
+}
+
+impl<T, const CAP: usize> DoubleEndedIterator for IterMut<'_, T, CAP> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next_back()
+            .map(|ptr| unsafe { &mut *ptr.as_ptr() })
+    }
+}
+
+impl<T, const CAP: usize, M, G> Drop for SmallObjectPool<T, CAP, M, G> {
+    fn drop(&mut self) {
+        if needs_drop::<T>() {
+            for (begin, len) in self.raw_chunks() {
+                unsafe { slice_from_raw_parts_mut(begin.as_ptr(), len).drop_in_place() };
+            }
+        }
+        let layouts = self.layouts.get_mut().drain(..);
+        for (block, layout) in self.blocks.get_mut().drain(..).zip(layouts) {
+            unsafe { free_block(block, layout) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocksource::BlockRecycler;
+
+    #[test]
+    fn test_small_object_pool() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        for i in 0..8 {
+            sop.push(i);
+        }
+    }
+
+    #[test]
+    fn test_small_object_pool_rewind() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        for i in 0..8 {
+            sop.push(i);
+        }
+        sop.rewind();
+        for i in 0..8 {
+            sop.push(i);
+        }
+    }
+
+    #[test]
+    fn test_small_object_pool_rewind_to_mark() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        for i in 0..6 {
+            sop.push(i);
+        }
+        sop.mark();
+        let marked = unsafe { sop.push_to_ptr(6) };
+        for i in 7..12 {
+            sop.push(i);
+        }
+        sop.rewind_to_mark();
+        assert_eq!(unsafe { sop.push_to_ptr(42) }, marked);
+    }
+
+    #[test]
+    fn test_small_object_pool_scope() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.push(0);
+        let ptr = unsafe { sop.push_to_ptr(1) };
+        sop.rewind();
+        sop.push(0);
+        let value = sop.scope(|pool| {
+            for i in 0..9 {
+                pool.push(i);
+            }
+            7
+        });
+        assert_eq!(value, 7);
+        assert_eq!(unsafe { sop.push_to_ptr(1) }, ptr);
+    }
+
+    #[test]
+    fn test_small_object_pool_scope_guard_nested() {
+        let mut sop = SmallObjectPool::<u32, 2>::new();
+        sop.push(0);
+        let outer = {
+            let mut guard = sop.enter_scope();
+            guard.push(1);
+            let inner = {
+                let mut guard = guard.enter_scope();
+                unsafe { guard.push_to_ptr(2) }
+            };
+            assert_eq!(unsafe { guard.push_to_ptr(3) }, inner);
+            inner
+        };
+        let first = unsafe { sop.push_to_ptr(4) };
+        assert_ne!(first, outer);
+    }
+
+    #[test]
+    fn test_small_object_pool_scope_rewinds_on_panic() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let start = unsafe { sop.push_to_ptr(0) };
+        sop.rewind();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            sop.scope(|pool| {
+                for i in 0..10 {
+                    pool.push(i);
+                }
+                panic!("early exit");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(unsafe { sop.push_to_ptr(0) }, start);
+    }
+
+    #[test]
+    fn test_small_object_pool_lazy_first_block() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        assert!(sop.blocks().is_empty());
+        sop.mark();
+        sop.rewind();
+        assert!(sop.blocks().is_empty());
+        let first = unsafe { sop.push_to_ptr(1) };
+        assert!(!sop.blocks().is_empty());
+        sop.rewind_to_mark();
+        assert_eq!(unsafe { sop.push_to_ptr(2) }, first);
+    }
+
+    #[test]
+    fn test_small_object_pool_alloc() {
+        let sop = SmallObjectPool::<Cell<u32>, 4>::new();
+        let refs: Vec<&Cell<u32>> = (0..10).map(|i| sop.alloc(Cell::new(i))).collect();
+        for (i, r) in refs.into_iter().enumerate() {
+            assert_eq!(r.get(), i as u32);
+            r.set(r.get() + 1);
+        }
+        let a = sop.alloc(Cell::new(1));
+        let b = sop.alloc(Cell::new(2));
+        a.set(a.get() + b.get());
+        assert_eq!(a.get(), 3);
+        assert_eq!(sop[10].get(), 3);
+    }
+
+    #[test]
+    fn test_small_object_pool_alloc_while_iterating_chunks() {
+        let sop = SmallObjectPool::<u64, 2>::new();
+        for i in 0..6 {
+            sop.alloc(i);
+        }
+        let mut seen = Vec::new();
+        for chunk in sop.chunks() {
+            // Appending blocks reallocates the table the chunks are read from.
+            for i in 0..8 {
+                sop.alloc(100 + i);
+            }
+            seen.extend_from_slice(chunk);
+        }
+        assert_eq!(seen, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(sop.len(), 30);
+    }
+
+    #[test]
+    fn test_small_object_pool_alloc_slice_copy() {
+        let sop = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        let a = sop.alloc_slice_copy(&[1, 2, 3]);
+        let b = sop.alloc_slice_copy(&[4, 5]);
+        assert_eq!(a, &[1, 2, 3]);
+        assert_eq!(b, &[4, 5]);
+        // `b` didn't fit after `a`, so it spilled into a fresh block.
+        assert_eq!(sop.position().block_index(), 1);
+        assert_eq!(sop.position().slot(), 2);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_small_object_pool_alloc_slice_fill_with() {
+        let sop = SmallObjectPool::<u32, 8>::new();
+        sop.alloc(0);
+        let slice = sop.alloc_slice_fill_with(8, |i| i as u32 * 2);
+        assert_eq!(slice, &[0, 2, 4, 6, 8, 10, 12, 14]);
+        assert!(sop.alloc_slice_fill_with(0, |_| 1).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_small_object_pool_alloc_slice_too_long() {
+        let sop = SmallObjectPool::<u32, 2>::new();
+        sop.alloc_slice_copy(&[1, 2, 3]);
+    }
+
+    #[derive(Default)]
+    struct Sum(u32);
+
+    impl BlockMeta<u32> for Sum {
+        fn record(&mut self, value: &u32) {
+            self.0 += value;
+        }
+    }
+
+    #[test]
+    fn test_small_object_pool_blocks_with_meta() {
+        let mut sop = SmallObjectPool::<u32, 4, Sum>::new();
+        assert_eq!(sop.blocks_with_meta().count(), 0);
+        for i in 0..6 {
+            sop.push(i);
+        }
+        let blocks: Vec<_> = sop
+            .blocks_with_meta()
+            .map(|(values, meta)| (values.to_vec(), meta.0))
+            .collect();
+        assert_eq!(blocks, vec![(vec![0, 1, 2, 3], 6), (vec![4, 5], 9)]);
+
+        sop.rewind();
+        sop.push(10);
+        let blocks: Vec<_> = sop
+            .blocks_with_meta()
+            .map(|(values, meta)| (values.to_vec(), meta.0))
+            .collect();
+        assert_eq!(blocks, vec![(vec![10], 10)]);
+    }
+
+    #[test]
+    fn test_small_object_pool_filtered_scan() {
+        let mut sop = SmallObjectPool::<u32, 4, MinMax<u32>>::new();
+        for i in 0..16 {
+            sop.push(i);
+        }
+        let scanned: Vec<_> = sop.filtered_scan(5..10).copied().collect();
+        assert_eq!(scanned, vec![5, 6, 7, 8, 9]);
+        let visited = sop
+            .blocks_with_meta()
+            .filter(|(_, meta)| meta.overlaps(&(5..10)))
+            .count();
+        assert_eq!(visited, 2);
+        assert_eq!(sop.filtered_scan(100..).count(), 0);
+    }
+
+    #[test]
+    fn test_small_object_pool_iter() {
+        let mut sop = SmallObjectPool::<u32, 3>::new();
+        assert_eq!(sop.iter().count(), 0);
+        for i in 0..8 {
+            sop.push(i);
+        }
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            sop.iter().rev().copied().collect::<Vec<_>>(),
+            (0..8).rev().collect::<Vec<_>>()
+        );
+        let mut iter = sop.iter();
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next_back(), Some(&7));
+        assert_eq!(
+            iter.copied().collect::<Vec<_>>(),
+            (1..7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_small_object_pool_iter_mut() {
+        let mut sop = SmallObjectPool::<u32, 3>::new();
+        for i in 0..7 {
+            sop.push(i);
+        }
+        sop.iter_mut().rev().for_each(|value| *value *= 2);
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            vec![0, 2, 4, 6, 8, 10, 12]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot allocate another block")]
+    fn test_small_object_pool_out_of_block_indices() {
+        let mut sop = SmallObjectPool::<u32, 2>::new();
+        // As if every earlier block index had been discarded.
+        sop.head = BlockIndex::MAX;
+        sop.current_index.set(BlockIndex::MAX);
+        assert_eq!(sop.try_push(0), Ok(()));
+        assert_eq!(sop.try_push(1), Ok(()));
+        assert_eq!(sop.try_push(2), Err(PoolFull(2)));
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0, 1]);
+        sop.push(2);
+    }
+
+    #[test]
+    fn test_small_object_pool_try_push_failing_alloc() {
+        let mut sop = SmallObjectPool::<u32, 2>::new();
+        crate::failing_alloc::fail_at(2);
+        for i in 0..2 {
+            assert_eq!(sop.try_push(i), Ok(()));
+        }
+        assert_eq!(sop.try_push(2), Err(PoolFull(2)));
+        assert_eq!(sop.try_push(3), Ok(()));
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_small_object_pool_validate() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.validate().unwrap();
+        assert_eq!(sop.block_count(), 0);
+        for i in 0..10 {
+            sop.push(i);
+            sop.validate().unwrap();
+        }
+        sop.mark();
+        assert_eq!(sop.block_count(), 3);
+        sop.rewind();
+        sop.validate().unwrap();
+        sop.rewind_to_mark();
+        sop.validate().unwrap();
+        assert_eq!(sop.block_count(), 3);
+    }
+
+    #[test]
+    fn test_small_object_pool_locate() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let outside = NonNull::from(&0u32);
+        assert!(!sop.contains(outside));
+        let ptrs: Vec<_> = (0..6).map(|i| unsafe { sop.push_to_ptr(i) }).collect();
+        for (i, ptr) in ptrs.iter().enumerate() {
+            let position = sop.locate(*ptr).unwrap();
+            assert_eq!(to_usize(position.block_index()), i / 4);
+            assert_eq!(slot_to_usize(position.slot()), i % 4);
+        }
+        assert!(!sop.contains(outside));
+        sop.rewind();
+        sop.push(0);
+        assert!(sop.contains(ptrs[0]));
+        assert!(!sop.contains(ptrs[1]));
+        assert!(!sop.contains(ptrs[5]));
+    }
+
+    #[test]
+    fn test_small_object_pool_get() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(sop.get(0), None);
+        for i in 0..10 {
+            sop.push(i);
+        }
+        for i in 0..10 {
+            assert_eq!(sop.get(i), Some(&(i as u32)));
+        }
+        assert_eq!(sop.get(10), None);
+        assert_eq!(sop.get(100), None);
+        sop[5] = 50;
+        *sop.get_mut(9).unwrap() += 1;
+        assert_eq!((sop[5], sop[9]), (50, 10));
+        sop.rewind();
+        sop.push(7);
+        assert_eq!(sop.get(0), Some(&7));
+        assert_eq!(sop.get(1), None);
+        assert_eq!(sop.get(5), None);
+    }
+
+    #[test]
+    fn test_small_object_pool_get_skips_unused_slots() {
+        let sop = SmallObjectPool::<u32, 4>::new();
+        sop.alloc_slice_copy(&[1, 2, 3]);
+        sop.alloc_slice_copy(&[4, 5]);
+        assert_eq!(sop.get(2), Some(&3));
+        assert_eq!(sop.get(3), None);
+        assert_eq!(sop.get(4), Some(&4));
+    }
+
+    #[test]
+    #[should_panic(expected = "no live element")]
+    fn test_small_object_pool_index_out_of_bounds() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.push(1);
+        let _ = sop[1];
+    }
+
+    #[test]
+    fn test_small_object_pool_pop() {
+        let mut sop = SmallObjectPool::<String, 4>::new();
+        assert_eq!(sop.pop(), None);
+        sop.extend((0..6).map(|i| i.to_string()));
+        assert_eq!(sop.pop().as_deref(), Some("5"));
+        assert_eq!(sop.pop().as_deref(), Some("4"));
+        assert_eq!(sop.pop().as_deref(), Some("3"));
+        sop.push(String::from("3"));
+        assert_eq!(
+            sop.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["0", "1", "2", "3"]
+        );
+        sop.validate().unwrap();
+    }
+
+    #[test]
+    fn test_small_object_pool_swap_remove() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        for i in 0..5 {
+            sop.push(i);
+        }
+        let five = sop.push_handle(5);
+        let mut moves = Vec::new();
+        let removed = sop.swap_remove_notify(1, &mut |old, new| moves.push((old, new)));
+        assert_eq!(removed, 1);
+        let [(old, new)] = moves[..] else {
+            panic!("expected one move, got {moves:?}");
+        };
+        assert_eq!(old, five);
+        assert_eq!(
+            (to_usize(new.block_index()), slot_to_usize(new.slot())),
+            (0, 1)
+        );
+        assert_eq!(sop.resolve(&old), None);
+        assert_eq!(sop.resolve(&new), Some(&5));
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0, 5, 2, 3, 4]);
+        assert_eq!(sop.swap_remove(4), 4);
+        assert_eq!(sop.swap_remove(3), 3);
+        assert_eq!(sop.position().block_index(), 0);
+        sop.validate().unwrap();
+        sop.push(9);
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0, 5, 2, 9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "no live element")]
+    fn test_small_object_pool_swap_remove_out_of_bounds() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.swap_remove(0);
+    }
+
+    #[test]
+    fn test_small_object_pool_chunks() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(sop.chunks().count(), 0);
+        for i in 0..10 {
+            sop.push(i);
+        }
+        let chunks: Vec<_> = sop.chunks().map(|chunk| chunk.to_vec()).collect();
+        assert_eq!(chunks, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+        for chunk in sop.chunks_mut() {
+            chunk.copy_from_slice(&vec![1; chunk.len()]);
+        }
+        assert_eq!(
+            sop.chunks()
+                .map(|chunk| chunk.iter().sum::<u32>())
+                .sum::<u32>(),
+            10
+        );
+        sop.rewind();
+        sop.push(5);
+        assert_eq!(sop.chunks().collect::<Vec<_>>(), vec![&[5][..]]);
+    }
+
+    #[test]
+    fn test_small_object_pool_iter_insertion_order() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        for i in 0..6 {
+            sop.push(i);
+        }
+        assert_eq!(
+            sop.iter_insertion_order().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        sop.swap_remove(1);
+        sop.push(6);
+        sop.alloc_slice_copy(&[70, 8]);
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            vec![0, 5, 2, 3, 4, 6, 70, 8]
+        );
+        assert_eq!(
+            sop.iter_insertion_order().copied().collect::<Vec<_>>(),
+            vec![0, 2, 3, 4, 5, 6, 70, 8]
+        );
+        sop.rewind();
+        sop.push(1);
+        assert_eq!(
+            sop.iter_insertion_order().copied().collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_small_object_pool_extend_from_slice() {
+        let mut sop = SmallObjectPool::<u32, 4, Sum>::new();
+        sop.push(0);
+        sop.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        sop.extend_from_slice(&[]);
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        let sums: Vec<_> = sop.blocks_with_meta().map(|(_, meta)| meta.0).collect();
+        assert_eq!(sums, vec![6, 22, 17]);
+        sop.validate().unwrap();
+    }
+
+    #[test]
+    fn test_small_object_pool_extend_and_collect() {
+        let mut sop: SmallObjectPool<u32, 3> = (0..5).collect();
+        sop.extend(5..7);
+        sop.extend(&[7, 8]);
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            (0..9).collect::<Vec<_>>()
+        );
+        assert_eq!(sop.block_count(), 3);
+    }
+
+    #[test]
+    fn test_small_object_pool_from_iter_with_len() {
+        let sop = SmallObjectPool::<u32, 4>::from_iter_with_len(0..10, 10);
+        assert_eq!(sop.block_count(), 3);
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        let sop = SmallObjectPool::<u32, 4>::from_iter_with_len(0..10, 2);
+        assert_eq!((sop.len(), sop.block_count()), (10, 3));
+        let sop = SmallObjectPool::<u32, 4>::from_iter_with_len(0..2, 10);
+        assert_eq!((sop.len(), sop.block_count()), (2, 3));
+        sop.validate().unwrap();
+        let sop = crate::growth::FixedPool::<u32, 4, 2>::from_iter_with_len(0..8, 100);
+        assert_eq!((sop.len(), sop.block_count()), (8, 2));
+    }
+
+    #[test]
+    fn test_small_object_pool_len_tracking() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(sop.len(), 0);
+        sop.extend(0..6);
+        sop.alloc(6);
+        assert_eq!(sop.len(), 7);
+        sop.mark();
+        sop.extend(7..13);
+        let end = sop.position();
+        assert_eq!(sop.pop(), Some(12));
+        assert_eq!(sop.swap_remove(1), 1);
+        assert_eq!(sop.len(), 11);
+        sop.rewind_to_mark();
+        assert_eq!(sop.len(), 7);
+        // Copy elements are still there, so the cursor can move forward again.
+        sop.rewind_to(end);
+        assert_eq!(sop.len(), 13);
+        sop.validate().unwrap();
+        let window = sop.position();
+        sop.extend(0..2);
+        sop.discard_before(window);
+        assert_eq!(sop.len(), sop.iter().count());
+        sop.validate().unwrap();
+        sop.rewind();
+        assert!(sop.is_empty());
+    }
+
+    #[test]
+    fn test_small_object_pool_discard_before() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.extend(0..6);
+        sop.mark();
+        let mut tail = sop.subscribe();
+        sop.extend(6..14);
+        let window = sop.position();
+        sop.extend(14..18);
+        assert_eq!(sop.discard_before(window), 3);
+        assert_eq!(sop.block_count(), 2);
+        sop.validate().unwrap();
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            (12..18).collect::<Vec<_>>()
+        );
+        assert_eq!(sop.get(11), None);
+        assert_eq!(sop.get(12), Some(&12));
+        assert_eq!(to_usize(sop.start().block_index()), 3);
+        assert_eq!(tail.poll(&sop).unwrap().count(), 6);
+        sop.rewind_to_mark();
+        sop.push(20);
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![20]);
+        assert_eq!(sop.discard_before(sop.start()), 0);
+    }
+
+    #[test]
+    fn test_small_object_pool_merge_append_only() {
+        let mut primary = SmallObjectPool::<u32, 4>::new();
+        let mut replica = SmallObjectPool::<u32, 4>::new();
+        primary.extend(0..6);
+        let seen = replica.merge_append_only(&primary, 0);
+        assert_eq!(seen, 6);
+        primary.extend(6..14);
+        let window = primary.position();
+        primary.extend(14..18);
+        let seen = replica.merge_append_only(&primary, seen);
+        assert_eq!(seen, 18);
+        primary.discard_before(window);
+        primary.extend(18..20);
+        assert_eq!(primary.next_seq(), 20);
+        let seen = replica.merge_append_only(&primary, seen);
+        assert_eq!(seen, 20);
+        assert_eq!(replica.merge_append_only(&primary, seen), 20);
+        assert_eq!(replica.next_seq(), 20);
+        assert_eq!(
+            replica.iter().copied().collect::<Vec<_>>(),
+            (0..20).collect::<Vec<_>>()
+        );
+        replica.validate().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "were discarded")]
+    fn test_small_object_pool_merge_after_discard() {
+        let mut primary = SmallObjectPool::<u32, 4>::new();
+        primary.extend(0..10);
+        primary.discard_before(primary.position());
+        SmallObjectPool::<u32, 4>::new().merge_append_only(&primary, 2);
+    }
+
+    #[test]
+    fn test_small_object_pool_donate_free_blocks() {
+        let mut sop = SmallObjectPool::<u64, 4>::new();
+        sop.extend(0..16);
+        assert!(sop.take_free_block().is_none());
+        sop.rewind();
+        sop.push(1);
+        let mut raw = crate::rawpool::RawPool::<32>::new();
+        assert_eq!(sop.donate_free_blocks(&mut raw), 3);
+        assert_eq!(sop.block_count(), 1);
+        assert_eq!(raw.block_count(), 3);
+        sop.validate().unwrap();
+
+        let mut other = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(raw.donate_free_blocks(&mut other), 3);
+        assert_eq!(other.block_count(), 3);
+        other.validate().unwrap();
+        other.extend(0..12);
+        assert_eq!(other.block_count(), 3);
+        assert_eq!(
+            other.iter().copied().collect::<Vec<_>>(),
+            (0..12).collect::<Vec<_>>()
+        );
+        other.validate().unwrap();
+    }
+
+    #[test]
+    fn test_small_object_pool_recycle() {
+        let layout = BlockRecycler::for_pool::<String, 4>().block_layout();
+        let mut recycler = BlockRecycler::with_limit(layout, 3);
+        let mut sop = SmallObjectPool::<String, 4>::with_capacity_from(10, &mut recycler);
+        assert_eq!(sop.block_layout(), layout);
+        assert!(layout.align() >= 16);
+        assert_eq!(sop.block_count(), 3);
+        sop.extend((0..18).map(|i| i.to_string()));
+        assert_eq!(sop.recycle(&mut recycler), 3);
+        assert_eq!(recycler.len(), 3);
+
+        let mut sop = SmallObjectPool::<String, 4>::with_capacity_from(6, &mut recycler);
+        assert_eq!((sop.block_count(), recycler.len()), (2, 1));
+        sop.extend((0..6).map(|i| i.to_string()));
+        assert_eq!(sop.iter().next_back().map(String::as_str), Some("5"));
+        sop.validate().unwrap();
+        assert_eq!(sop.recycle(&mut recycler), 2);
+
+        // Blocks of another layout are left to the recycler.
+        let other = SmallObjectPool::<[u64; 4], 4>::with_capacity_from(4, &mut recycler);
+        assert_eq!((other.block_count(), recycler.len()), (1, 3));
+    }
+
+    #[test]
+    fn test_small_object_pool_handles() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.push(0);
+        sop.mark();
+        let handles: Vec<_> = (1..7).map(|i| sop.push_handle(i)).collect();
+        assert_eq!(sop.resolve(&handles[4]), Some(&5));
+        *sop.resolve_mut(&handles[0]).unwrap() = 10;
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            vec![0, 10, 2, 3, 4, 5, 6]
+        );
+        let other = SmallObjectPool::<u32, 4>::new();
+        assert_eq!(other.resolve(&handles[0]), None);
+        sop.rewind_to_mark();
+        assert!(handles.iter().all(|handle| sop.resolve(handle).is_none()));
+        sop.push(11);
+        assert_eq!(sop.resolve(&handles[0]), None);
+        assert_eq!(sop.resolve(&handles[1]), None);
+    }
+
+    #[test]
+    fn test_small_object_pool_stale_handles() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let handles: Vec<_> = (0..6).map(|i| sop.push_handle(i)).collect();
+        assert_eq!(sop.swap_remove(1), 1);
+        assert_eq!(sop.resolve(&handles[1]), None);
+        assert_eq!(sop.resolve(&handles[5]), None);
+        assert_eq!(sop.resolve(&handles[0]), Some(&0));
+        let moved = sop.push_handle(6);
+        assert_eq!(sop.resolve(&moved), Some(&6));
+
+        // A block freed and allocated again is told apart by its serial.
+        sop.rewind_to(sop.start());
+        sop.shrink_to_fit();
+        sop.extend(0..6);
+        assert!(handles[4..]
+            .iter()
+            .all(|handle| sop.resolve(handle).is_none()));
+        assert_eq!(sop.resolve(&moved), None);
+    }
+
+    #[test]
+    fn test_small_object_pool_swap() {
+        let mut sop = SmallObjectPool::<u32, 4, MinMax<u32>>::new();
+        let handles: Vec<_> = (0..6).map(|i| sop.push_handle(i * 10)).collect();
+        sop.swap(&handles[1], &handles[5]).unwrap();
+        sop.swap(&handles[2], &handles[2]).unwrap();
+        assert_eq!(
+            sop.iter().copied().collect::<Vec<_>>(),
+            vec![0, 50, 20, 30, 40, 10]
+        );
+        assert_eq!(sop.resolve(&handles[5]), Some(&10));
+        assert_eq!(
+            sop.iter_insertion_order().copied().collect::<Vec<_>>(),
+            vec![0, 10, 20, 30, 40, 50]
+        );
+        assert_eq!(
+            sop.filtered_scan(45..55).copied().collect::<Vec<_>>(),
+            vec![50]
+        );
+        sop.validate().unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "abort-on-violation"))]
+    fn test_small_object_pool_swap_stale_handle() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let a = sop.push_handle(0);
+        sop.mark();
+        let b = sop.push_handle(1);
+        sop.rewind_to_mark();
+        // Panics or returns the violation, depending on the policy in effect.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| sop.swap(&a, &b)));
+        assert!(!matches!(result, Ok(Ok(()))));
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn test_small_object_pool_statistics() {
+        let mut sop = SmallObjectPool::<u64, 4>::new();
+        assert!(sop.is_empty());
+        assert_eq!(
+            (sop.len(), sop.capacity(), sop.allocated_bytes()),
+            (0, 0, 0)
+        );
+        sop.extend(0..6);
+        sop.mark();
+        sop.push(6);
+        assert_eq!((sop.len(), sop.capacity(), sop.block_count()), (7, 8, 2));
+        assert!(sop.allocated_bytes() >= 2 * 4 * 8);
+        sop.rewind_to_mark();
+        assert_eq!(sop.len(), 6);
+        sop.alloc_slice_copy(&[1, 2, 3]);
+        assert_eq!(sop.len(), 9);
+        sop.rewind();
+        assert!(sop.is_empty());
+        sop.push(0);
+        assert_eq!(sop.high_water_mark(), 9);
+        assert_eq!(sop.capacity(), 12);
+    }
+
+    #[test]
+    fn test_small_object_pool_reserve() {
+        let mut sop = SmallObjectPool::<u32, 4>::with_capacity(10);
+        assert_eq!(sop.block_count(), 3);
+        sop.extend(0..3);
+        sop.reserve(9);
+        assert_eq!(sop.block_count(), 3);
+        sop.reserve(10);
+        assert_eq!(sop.block_count(), 4);
+        crate::failing_alloc::fail_at(1);
+        for i in 3..13 {
+            assert!(sop.try_push(i).is_ok());
+        }
+        crate::failing_alloc::disarm();
+        assert_eq!(sop.len(), 13);
+        sop.validate().unwrap();
+    }
+
+    #[test]
+    fn test_small_object_pool_uninitialized_slots() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.push(0);
+        sop.mark();
+        let (single, pair) = unsafe { (sop.emplace_back(), sop.emplace_back_multi::<2>()) };
+        sop.push(3);
+        assert_eq!(sop.uninitialized_len(), 3);
+        unsafe {
+            single.write(1);
+            sop.commit_emplaced(single, 1);
+            pair.write(4);
+            pair.add(1).write(5);
+            sop.commit_emplaced(pair, 2);
+        }
+        sop.assert_fully_initialized();
+        assert_eq!(unsafe { sop.blocks()[0].as_ref() }.initialized_len(), 4);
+        assert_eq!(sop.iter().copied().collect::<Vec<_>>(), vec![0, 1, 4, 5, 3]);
+
+        sop.rewind_to_mark();
+        unsafe { sop.emplace_back() };
+        assert_eq!(sop.uninitialized_len(), 1);
+        sop.rewind_to_mark();
+        sop.assert_fully_initialized();
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved slots were never committed")]
+    fn test_small_object_pool_uncommitted_slot() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        unsafe { sop.emplace_back_multi::<3>() };
+        sop.push(3);
+        sop.assert_fully_initialized();
+    }
+
+    #[test]
+    fn test_small_object_pool_shrink_to_fit() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.extend(0..5);
+        sop.mark();
+        sop.extend(5..20);
+        sop.rewind_to_mark();
+        assert_eq!(sop.shrink_to_fit(), 3);
+        assert_eq!(sop.block_count(), 2);
+        sop.validate().unwrap();
+        sop.extend(5..10);
+        assert_eq!(sop.len(), 10);
+        sop.rewind();
+        assert_eq!(sop.shrink_to_fit(), 1);
+        assert_eq!(sop.rewind_and_free(), 1);
+        assert_eq!(sop.block_count(), 1);
+        sop.validate().unwrap();
+        sop.rewind_to_mark();
+        sop.extend(0..6);
+        assert_eq!(sop.iter().count(), 6);
+    }
+
+    #[test]
+    fn test_small_object_pool_stale_position_after_shrink() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.extend(0..10);
+        let past = sop.position();
+        sop.rewind();
+        assert_eq!(sop.shrink_to_fit(), 2);
+        sop.extend(0..11);
+        // Blocks 1 and 2 were allocated again, possibly at the same addresses.
+        assert!(sop.is_stale(past));
+        assert!(!sop.is_stale(sop.start()) && !sop.is_stale(sop.position()));
+        sop.rewind_to(past);
+        assert_eq!(sop.len(), 11);
+        sop.validate().unwrap();
+    }
+
+    /// Counts how many of its values have been dropped.
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_small_object_pool_drops_removed_elements() {
+        let drops = Cell::new(0);
+        let mut sop = SmallObjectPool::<DropCounter, 4>::new();
+        sop.extend((0..3).map(|_| DropCounter(&drops)));
+        sop.mark();
+        sop.extend((0..7).map(|_| DropCounter(&drops)));
+        sop.rewind_to_mark();
+        assert_eq!(drops.get(), 7);
+        sop.rewind();
+        assert_eq!(drops.get(), 10);
+        // The marked elements are gone, so the cursor doesn't move forward.
+        sop.rewind_to_mark();
+        assert!(sop.is_empty());
+
+        sop.extend((0..5).map(|_| DropCounter(&drops)));
+        sop.scope(|sop| sop.extend((0..2).map(|_| DropCounter(&drops))));
+        assert_eq!(drops.get(), 12);
+        drop(sop.swap_remove(0));
+        assert_eq!((drops.get(), sop.len()), (13, 4));
+
+        sop.extend((0..3).map(|_| DropCounter(&drops)));
+        assert_eq!(sop.discard_before(sop.position()), 1);
+        assert_eq!((drops.get(), sop.len()), (17, 3));
+        drop(sop);
+        assert_eq!(drops.get(), 20);
+    }
+
+    #[test]
+    fn test_small_object_pool_fill() {
+        let mut sop: SmallObjectPool<f64, 4> = (0..10).map(f64::from).collect();
+        sop.fill(1.5);
+        assert!(sop.iter().all(|&x| x == 1.5));
+        assert_eq!(sop.len(), 10);
+    }
+
+    #[test]
+    #[cfg(feature = "bytemuck")]
+    fn test_small_object_pool_zero_all() {
+        let mut sop: SmallObjectPool<[f32; 3], 4> = (0..10).map(|i| [i as f32; 3]).collect();
+        sop.zero_all();
+        assert!(sop.iter().all(|&x| x == [0.0; 3]));
     }
 }
@@ -0,0 +1,214 @@
+use std::mem::{needs_drop, MaybeUninit};
+
+use crate::{
+    blockpool::BlockPool,
+    policy::{self, ensure, Violation},
+    smallobjectpool::PoolFull,
+};
+
+/// # StaticPool
+/// A pool of `BLOCKS` blocks of `CAP` elements stored in the pool itself,
+/// with no heap allocation at all. `new` is a `const fn`, so the pool can
+/// live in a `static`, e.g. behind a `Mutex`. Pushing to a full pool hands
+/// the value back.
+pub struct StaticPool<T, const CAP: usize, const BLOCKS: usize> {
+    blocks: [[MaybeUninit<T>; CAP]; BLOCKS],
+    len: usize,
+    marked: usize,
+}
+
+impl<T, const CAP: usize, const BLOCKS: usize> StaticPool<T, CAP, BLOCKS> {
+    pub const fn new() -> Self {
+        StaticPool {
+            blocks: [const { [const { MaybeUninit::uninit() }; CAP] }; BLOCKS],
+            len: 0,
+            marked: 0,
+        }
+    }
+
+    /// Number of blocks held by the pool, all of them embedded in it.
+    pub const fn block_count(&self) -> usize {
+        BLOCKS
+    }
+
+    /// Number of elements pushed since the start of the pool
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the pool can hold
+    pub const fn capacity(&self) -> usize {
+        CAP * BLOCKS
+    }
+
+    fn slot(&mut self, index: usize) -> &mut MaybeUninit<T> {
+        &mut self.blocks[index / CAP][index % CAP]
+    }
+
+    /// Push a value at the end of the pool, handing it back if the pool is full.
+    pub fn push(&mut self, value: T) -> Result<(), PoolFull<T>> {
+        if self.len == self.capacity() {
+            return Err(PoolFull(value));
+        }
+        self.slot(self.len).write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len)
+            .then(|| unsafe { self.blocks[index / CAP][index % CAP].assume_init_ref() })
+    }
+
+    /// The element with the given index, if it has been pushed.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        (index < self.len).then(|| unsafe { self.slot(index).assume_init_mut() })
+    }
+
+    /// Iterate over the elements pushed since the start of the pool.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.blocks
+            .as_flattened()
+            .iter()
+            .take(self.len)
+            .map(|slot| unsafe { slot.assume_init_ref() })
+    }
+
+    /// Drop the elements from `index` on and move the cursor back to it. The
+    /// cursor never moves forward.
+    fn rewind_to(&mut self, index: usize) {
+        let end = self.len;
+        self.len = index.min(end);
+        if needs_drop::<T>() {
+            for index in self.len..end {
+                unsafe { self.slot(index).assume_init_drop() };
+            }
+        }
+    }
+
+    /// Rewind to the first block
+    pub fn rewind(&mut self) {
+        self.rewind_to(0);
+    }
+
+    /// Mark the current position
+    pub fn mark(&mut self) {
+        self.marked = self.len;
+    }
+
+    /// Rewind to the marked position
+    pub fn rewind_to_mark(&mut self) {
+        self.rewind_to(self.marked);
+    }
+
+    /// Check the pool's internal invariants. A broken one is a violation
+    /// under the crate's `Policy`.
+    pub fn validate(&self) -> Result<(), Violation> {
+        ensure!(
+            self.len <= self.capacity(),
+            Violation::BrokenInvariant,
+            "the cursor is past the last block"
+        );
+        Ok(())
+    }
+}
+
+impl<T, const CAP: usize, const BLOCKS: usize> BlockPool for StaticPool<T, CAP, BLOCKS> {
+    type Item = T;
+
+    /// Push a value, failing as the crate's `Policy` says if the pool is full.
+    fn push(&mut self, value: T) {
+        if StaticPool::push(self, value).is_err() {
+            policy::fail(format_args!(
+                "{}: it holds {} elements",
+                Violation::PoolFull,
+                self.capacity()
+            ));
+        }
+    }
+
+    fn mark(&mut self) {
+        StaticPool::mark(self)
+    }
+
+    fn rewind(&mut self) {
+        StaticPool::rewind(self)
+    }
+
+    fn rewind_to_mark(&mut self) {
+        StaticPool::rewind_to_mark(self)
+    }
+
+    fn block_count(&self) -> usize {
+        StaticPool::block_count(self)
+    }
+
+    fn validate(&self) -> Result<(), Violation> {
+        StaticPool::validate(self)
+    }
+}
+
+impl<T, const CAP: usize, const BLOCKS: usize> Default for StaticPool<T, CAP, BLOCKS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize, const BLOCKS: usize> Drop for StaticPool<T, CAP, BLOCKS> {
+    fn drop(&mut self) {
+        self.rewind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    static EVENTS: Mutex<StaticPool<(u8, u32), 4, 2>> = Mutex::new(StaticPool::new());
+
+    #[test]
+    fn test_static_pool_in_a_static() {
+        let mut events = EVENTS.lock().unwrap();
+        events.mark();
+        for i in 0..8 {
+            assert_eq!(events.push((1, i)), Ok(()));
+        }
+        assert_eq!(events.push((1, 8)), Err(PoolFull((1, 8))));
+        assert_eq!(events.get(5), Some(&(1, 5)));
+        assert_eq!(events.iter().next_back(), Some(&(1, 7)));
+        events.rewind_to_mark();
+        assert!(events.is_empty());
+        events.validate().unwrap();
+    }
+
+    #[test]
+    fn test_static_pool_drops() {
+        let value = std::rc::Rc::new(());
+        let mut pool = StaticPool::<_, 2, 2>::new();
+        for _ in 0..3 {
+            pool.push(value.clone()).unwrap();
+        }
+        pool.mark();
+        pool.push(value.clone()).unwrap();
+        pool.rewind_to_mark();
+        assert_eq!(std::rc::Rc::strong_count(&value), 4);
+        drop(pool);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the pool is full")]
+    fn test_static_pool_full_through_block_pool() {
+        let _policy = policy::default_policy();
+        let mut pool = StaticPool::<u32, 2, 1>::new();
+        (0..3).for_each(|i| BlockPool::push(&mut pool, i));
+    }
+}
@@ -0,0 +1,115 @@
+use std::thread::{self, ThreadId};
+
+/// # SyncPool
+/// Wraps a pool so it can be shared with other threads, in an `Arc` or a
+/// struct that must be `Sync`, while only the thread owning it uses it. The
+/// pools move their cursor through a shared borrow, so they can't be `Sync`
+/// themselves: `get` hands out the pool on the owning thread only. A thread
+/// with exclusive access to the wrapper can always use the pool
+/// (`get_mut`, `into_inner`) or become its owner (`claim`).
+pub struct SyncPool<P> {
+    pool: P,
+    owner: ThreadId,
+}
+
+// Through a shared borrow, the pool is only reachable from the owning thread,
+// so it is never used from two threads at once.
+unsafe impl<P: Send> Sync for SyncPool<P> {}
+
+impl<P> SyncPool<P> {
+    /// Wrap `pool`, owned by the current thread.
+    pub fn new(pool: P) -> Self {
+        SyncPool {
+            pool,
+            owner: thread::current().id(),
+        }
+    }
+
+    /// The thread allowed to use the pool through a shared borrow.
+    pub fn owner(&self) -> ThreadId {
+        self.owner
+    }
+
+    pub fn is_owner(&self) -> bool {
+        thread::current().id() == self.owner
+    }
+
+    /// The pool, if the current thread owns it.
+    pub fn get(&self) -> Option<&P> {
+        self.is_owner().then_some(&self.pool)
+    }
+
+    /// The pool, from any thread: no other thread can be using it.
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.pool
+    }
+
+    /// Make the current thread the owner, e.g. after moving the wrapper to it.
+    pub fn claim(&mut self) {
+        self.owner = thread::current().id();
+    }
+
+    pub fn into_inner(self) -> P {
+        self.pool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        blocksource::BlockMemory, dynpool::DynPool, linkedlist::LinkedList, rawpool::RawPool,
+        smallobjectpool::SmallObjectPool,
+    };
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn test_syncpool_send_pools() {
+        assert_send::<SmallObjectPool<String, 4>>();
+        assert_send::<crate::smallobjectpool::IterMut<'_, String, 4>>();
+        assert_send::<DynPool<String>>();
+        assert_send::<RawPool<64>>();
+        assert_send::<BlockMemory>();
+        assert_send::<LinkedList<String>>();
+        assert_sync::<SyncPool<SmallObjectPool<String, 4>>>();
+
+        let mut pool = SmallObjectPool::<String, 4>::new();
+        pool.push("main".to_string());
+        let pool = thread::spawn(move || {
+            pool.extend((0..5).map(|i| i.to_string()));
+            pool
+        })
+        .join()
+        .unwrap();
+        assert_eq!(pool.len(), 6);
+        assert_eq!(pool[5], "4");
+    }
+
+    #[test]
+    fn test_syncpool_confined_to_owner() {
+        let shared = Arc::new(SyncPool::new(SmallObjectPool::<u32, 4>::new()));
+        shared.get().unwrap().alloc(1);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                assert!(!shared.is_owner());
+                assert!(shared.get().is_none());
+            });
+        });
+        assert_eq!(shared.get().unwrap().len(), 1);
+
+        let mut wrapper = Arc::into_inner(shared).unwrap();
+        let pool = thread::spawn(move || {
+            wrapper.claim();
+            wrapper.get().unwrap().alloc(2);
+            wrapper.get_mut().push(3);
+            wrapper.into_inner()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(pool.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    blockmeta::BlockMeta,
+    growth::Growth,
+    policy::{self, Violation},
+    smallobjectpool::{Iter, Position, SmallObjectPool},
+};
+
+/// # Tail
+/// A reader that yields the elements appended to a pool since it was last polled.
+/// If the pool is rewound behind the tail, or the tail's block is freed, the
+/// tail restarts from the new cursor; elements overwritten in between are not
+/// reported.
+pub struct Tail<T, const CAP: usize> {
+    pool_id: usize,
+    position: Position<T, CAP>,
+}
+
+impl<T, const CAP: usize> Tail<T, CAP> {
+    pub fn new<M: BlockMeta<T>, G: Growth>(pool: &SmallObjectPool<T, CAP, M, G>) -> Self {
+        Tail {
+            pool_id: pool.id(),
+            position: pool.position(),
+        }
+    }
+
+    /// Position up to which the tail has read, e.g. to prune everything
+    /// before it with `SmallObjectPool::discard_before`.
+    pub fn position(&self) -> Position<T, CAP> {
+        self.position
+    }
+
+    /// Iterate over the elements pushed since the last poll. Polling with a
+    /// pool other than the one the tail was taken from is a violation,
+    /// handled as the crate's `Policy` says.
+    pub fn poll<'a, M: BlockMeta<T>, G: Growth>(
+        &mut self,
+        pool: &'a SmallObjectPool<T, CAP, M, G>,
+    ) -> Result<Iter<'a, T, CAP>, Violation> {
+        if self.pool_id != pool.id() {
+            return Err(policy::violate(Violation::ForeignPool));
+        }
+        let current = pool.position();
+        let from = pool.normalize(self.position);
+        let from = if from > current { current } else { from };
+        self.position = current;
+        Ok(pool.iter_between(from, current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_poll() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        sop.push(100);
+        let mut tail = sop.subscribe();
+        assert_eq!(tail.poll(&sop).unwrap().count(), 0);
+        for i in 0..6 {
+            sop.push(i);
+        }
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        sop.alloc(6);
+        sop.alloc(7);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![6, 7]
+        );
+        assert_eq!(tail.poll(&sop).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_tail_after_rewind() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        for i in 0..9 {
+            sop.push(i);
+        }
+        assert_eq!(tail.poll(&sop).unwrap().count(), 9);
+        sop.rewind();
+        assert_eq!(tail.poll(&sop).unwrap().count(), 0);
+        sop.push(42);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![42]
+        );
+    }
+
+    #[test]
+    fn test_tail_before_first_push() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        assert_eq!(tail.poll(&sop).unwrap().count(), 0);
+        sop.push(1);
+        sop.push(2);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_tail_prunes_read_blocks() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        sop.extend(0..10);
+        assert_eq!(tail.poll(&sop).unwrap().count(), 10);
+        assert_eq!(sop.discard_before(tail.position()), 2);
+        sop.push(10);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![10]
+        );
+    }
+
+    #[test]
+    fn test_tail_after_shrink() {
+        let mut sop = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        sop.extend(0..10);
+        assert_eq!(tail.poll(&sop).unwrap().count(), 10);
+        sop.rewind();
+        assert_eq!(sop.shrink_to_fit(), 2);
+        sop.extend(0..11);
+        // The tail's block was freed, so it restarts from the cursor.
+        assert_eq!(tail.poll(&sop).unwrap().count(), 0);
+        sop.push(11);
+        assert_eq!(
+            tail.poll(&sop).unwrap().copied().collect::<Vec<_>>(),
+            vec![11]
+        );
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "it was taken from another pool")]
+    fn test_tail_other_pool() {
+        let _policy = policy::default_policy();
+        let sop = SmallObjectPool::<u32, 4>::new();
+        let other = SmallObjectPool::<u32, 4>::new();
+        let mut tail = sop.subscribe();
+        let _ = tail.poll(&other);
+    }
+}
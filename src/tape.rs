@@ -0,0 +1,613 @@
+use std::{cell::RefCell, marker::PhantomPinned, pin::Pin, ptr::NonNull};
+
+use crate::{
+    node::ADNode,
+    policy::{self, Violation},
+    smallobjectpool::{Position, SmallObjectPool},
+};
+
+/// Number of nodes per block of the tape.
+pub const NODE_BLOCK: usize = 1024;
+/// Number of derivatives (and adjoint pointers) per block of the tape.
+pub const DERIVATIVE_BLOCK: usize = 4096;
+
+/// # Tape
+/// Records `ADNode`s in a pool, with their derivatives and adjoint pointers in
+/// companion pools, and propagates adjoints backwards through them.
+pub struct Tape {
+    nodes: SmallObjectPool<ADNode, NODE_BLOCK>,
+    derivatives: SmallObjectPool<f64, DERIVATIVE_BLOCK>,
+    adj_ptrs: SmallObjectPool<NonNull<f64>, DERIVATIVE_BLOCK>,
+    _pinned: PhantomPinned,
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Tape {
+            nodes: SmallObjectPool::new(),
+            derivatives: SmallObjectPool::new(),
+            adj_ptrs: SmallObjectPool::new(),
+            _pinned: PhantomPinned,
+        }
+    }
+
+    /// Create a tape with room for `nodes` nodes taking `arguments` arguments in total.
+    pub fn with_capacity(nodes: usize, arguments: usize) -> Self {
+        let mut tape = Self::new();
+        tape.reserve(nodes, arguments);
+        tape
+    }
+
+    /// Allocate enough blocks to record `nodes` more nodes taking `arguments`
+    /// more arguments in total without allocating. A node's arguments are
+    /// stored contiguously, so nodes with several of them may need a little more.
+    pub fn reserve(&mut self, nodes: usize, arguments: usize) {
+        self.nodes.reserve(nodes);
+        self.derivatives.reserve(arguments);
+        self.adj_ptrs.reserve(arguments);
+    }
+
+    /// Record a node without arguments, e.g. an input.
+    pub fn record_leaf(&self) -> NonNull<ADNode> {
+        unsafe { self.record(&[]) }
+    }
+
+    /// Record a node with the given arguments and local derivatives. Adjoints
+    /// are only touched through raw pointers, so recording and propagating
+    /// need a shared borrow only; rewinding needs an exclusive one.
+    ///
+    /// # Safety
+    /// Every argument must be a node of this tape that has not been rewound.
+    ///
+    /// # Panics
+    /// If there are more than `DERIVATIVE_BLOCK` arguments.
+    pub unsafe fn record(&self, arguments: &[(NonNull<ADNode>, f64)]) -> NonNull<ADNode> {
+        let n = arguments.len();
+        let derivatives = self
+            .derivatives
+            .alloc_slice_fill_with(n, |i| arguments[i].1);
+        let adj_ptrs = self
+            .adj_ptrs
+            .alloc_slice_fill_with(n, |i| ADNode::adjoint_ptr(arguments[i].0));
+        // Derivatives and adjoint pointers are only read; adjoints are written
+        // through the node pointer, so it must not come from a shared reference.
+        let node = ADNode::new(
+            n,
+            NonNull::from(derivatives).cast(),
+            NonNull::from(adj_ptrs).cast(),
+        );
+        self.nodes.alloc_ptr(node)
+    }
+
+    /// Number of blocks held by the node, derivative and adjoint pointer pools.
+    pub fn block_count(&self) -> usize {
+        self.nodes.block_count() + self.derivatives.block_count() + self.adj_ptrs.block_count()
+    }
+
+    /// Whether `node` was recorded on this tape and not rewound since.
+    pub fn contains(&self, node: NonNull<ADNode>) -> bool {
+        self.nodes.contains(node)
+    }
+
+    /// Number of nodes recorded.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Number of node slots in the blocks held by the tape.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Bytes allocated for the blocks of the node, derivative and adjoint pointer pools.
+    pub fn allocated_bytes(&self) -> usize {
+        self.nodes.allocated_bytes()
+            + self.derivatives.allocated_bytes()
+            + self.adj_ptrs.allocated_bytes()
+    }
+
+    /// Largest number of nodes the tape has held at once.
+    pub fn high_water_mark(&self) -> usize {
+        self.nodes.high_water_mark()
+    }
+
+    /// Set every adjoint on the tape to zero.
+    pub fn reset_adjoints(&self) {
+        for node in self
+            .nodes
+            .raw_between(self.nodes.start(), self.nodes.position())
+        {
+            unsafe { (*node.as_ptr()).adjoint = 0.0 };
+        }
+    }
+
+    /// Propagate adjoints from the last node back to the first.
+    pub fn propagate_all(&self) {
+        let (start, end) = (self.nodes.start(), self.nodes.position());
+        self.propagate(start, end);
+    }
+
+    /// Propagate adjoints from the last node back to the mark.
+    pub fn propagate_to_mark(&self) {
+        let (start, end) = (self.nodes.marked(), self.nodes.position());
+        self.propagate(start, end);
+    }
+
+    /// Propagate adjoints from the mark back to the first node.
+    pub fn propagate_mark_to_start(&self) {
+        let (start, end) = (self.nodes.start(), self.nodes.marked());
+        self.propagate(start, end);
+    }
+
+    fn propagate(&self, start: Position<ADNode, NODE_BLOCK>, end: Position<ADNode, NODE_BLOCK>) {
+        // Nodes are walked through raw pointers since propagating one writes
+        // to the adjoints of earlier ones.
+        for node in self.nodes.raw_between(start, end).rev() {
+            unsafe { node.as_ref().propagate_one() };
+        }
+    }
+
+    /// Capture the current end of the tape.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            tape_id: self.nodes.id(),
+            nodes: self.nodes.position(),
+            derivatives: self.derivatives.position(),
+            adj_ptrs: self.adj_ptrs.position(),
+        }
+    }
+
+    /// Check that `checkpoint` was taken on this tape and still falls in it:
+    /// not past its end, nor in blocks freed by `shrink_to_fit` since. A
+    /// stale or foreign checkpoint is a violation under the crate's `Policy`.
+    #[track_caller]
+    fn validate(&self, checkpoint: &Checkpoint) -> Result<(), Violation> {
+        if checkpoint.tape_id != self.nodes.id() {
+            return Err(policy::violate(Violation::ForeignCheckpoint));
+        }
+        let stale = self.nodes.is_stale(checkpoint.nodes)
+            || self.derivatives.is_stale(checkpoint.derivatives)
+            || self.adj_ptrs.is_stale(checkpoint.adj_ptrs)
+            || self.nodes.normalize(checkpoint.nodes) > self.nodes.position();
+        if stale {
+            return Err(policy::violate(Violation::StaleCheckpoint));
+        }
+        Ok(())
+    }
+
+    /// Drop every node recorded after `checkpoint`. Checkpoints taken after it
+    /// are invalidated once new nodes are recorded; the tape is left as it is
+    /// if `checkpoint` is stale.
+    #[track_caller]
+    pub fn rewind_to(&mut self, checkpoint: Checkpoint) -> Result<(), Violation> {
+        self.validate(&checkpoint)?;
+        self.nodes.rewind_to(checkpoint.nodes);
+        self.derivatives.rewind_to(checkpoint.derivatives);
+        self.adj_ptrs.rewind_to(checkpoint.adj_ptrs);
+        Ok(())
+    }
+
+    /// Propagate adjoints from `to` back to `from`, over the nodes recorded
+    /// between the two checkpoints, which must be given in the order they
+    /// were taken.
+    #[track_caller]
+    pub fn propagate_between(&self, from: Checkpoint, to: Checkpoint) -> Result<(), Violation> {
+        self.validate(&from)?;
+        self.validate(&to)?;
+        if self.nodes.normalize(from.nodes) > self.nodes.normalize(to.nodes) {
+            return Err(policy::violate(Violation::UnorderedCheckpoints));
+        }
+        self.propagate(from.nodes, to.nodes);
+        Ok(())
+    }
+
+    /// Mark the current end of the tape.
+    pub fn mark(&mut self) {
+        self.nodes.mark();
+        self.derivatives.mark();
+        self.adj_ptrs.mark();
+    }
+
+    /// Drop every node recorded after the mark.
+    pub fn rewind_to_mark(&mut self) {
+        self.nodes.rewind_to_mark();
+        self.derivatives.rewind_to_mark();
+        self.adj_ptrs.rewind_to_mark();
+    }
+
+    /// Drop every node.
+    pub fn rewind(&mut self) {
+        self.nodes.rewind();
+        self.derivatives.rewind();
+        self.adj_ptrs.rewind();
+    }
+
+    /// Free the blocks past the end of the tape and its mark, e.g. after a
+    /// large recording that won't be repeated. Returns how many were freed.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        self.nodes.shrink_to_fit()
+            + self.derivatives.shrink_to_fit()
+            + self.adj_ptrs.shrink_to_fit()
+    }
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # Checkpoint
+/// A position on a tape, captured by `Tape::checkpoint`.
+#[derive(Clone, Copy, Debug)]
+pub struct Checkpoint {
+    tape_id: usize,
+    nodes: Position<ADNode, NODE_BLOCK>,
+    derivatives: Position<f64, DERIVATIVE_BLOCK>,
+    adj_ptrs: Position<NonNull<f64>, DERIVATIVE_BLOCK>,
+}
+
+thread_local! {
+    /// Tapes activated on this thread, innermost last.
+    static ACTIVE: RefCell<Vec<*const Tape>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Tape {
+    /// Make this tape the active one on the current thread until the returned
+    /// guard is dropped, at which point the previously active tape is restored.
+    ///
+    /// The tape has to be pinned so that it can't move while it is active,
+    /// even if the guard is leaked: a pinned tape is only released through
+    /// its `Drop`, which deactivates it. Its cursor operations stay available through
+    /// `Pin<&mut Tape>` as `mark_pinned`, `rewind_to_pinned` and so on, once
+    /// the tape is no longer active.
+    pub fn activate(self: Pin<&Self>) -> ActiveTape<'_> {
+        let tape = self.get_ref();
+        ACTIVE.with(|active| active.borrow_mut().push(tape));
+        ActiveTape { tape }
+    }
+
+    /// The tape as a mutable reference, for the operations that only move
+    /// its cursors and never the tape itself.
+    ///
+    /// # Panics
+    /// If the tape is still active, e.g. because its guard was leaked, since
+    /// `with_active` could then hand out a shared reference to it.
+    #[track_caller]
+    fn pinned_mut(self: Pin<&mut Self>) -> &mut Self {
+        let this: *const Tape = &*self;
+        let active = ACTIVE.with(|active| active.borrow().contains(&this));
+        assert!(!active, "the tape is still active on this thread");
+        // None of the callers moves the tape out of the reference.
+        unsafe { self.get_unchecked_mut() }
+    }
+
+    /// `reserve` on a pinned tape.
+    #[track_caller]
+    pub fn reserve_pinned(self: Pin<&mut Self>, nodes: usize, arguments: usize) {
+        self.pinned_mut().reserve(nodes, arguments);
+    }
+
+    /// `mark` on a pinned tape, e.g. one that has been activated.
+    #[track_caller]
+    pub fn mark_pinned(self: Pin<&mut Self>) {
+        self.pinned_mut().mark();
+    }
+
+    /// `rewind_to_mark` on a pinned tape.
+    #[track_caller]
+    pub fn rewind_to_mark_pinned(self: Pin<&mut Self>) {
+        self.pinned_mut().rewind_to_mark();
+    }
+
+    /// `rewind` on a pinned tape.
+    #[track_caller]
+    pub fn rewind_pinned(self: Pin<&mut Self>) {
+        self.pinned_mut().rewind();
+    }
+
+    /// `rewind_to` on a pinned tape.
+    #[track_caller]
+    pub fn rewind_to_pinned(self: Pin<&mut Self>, checkpoint: Checkpoint) -> Result<(), Violation> {
+        self.pinned_mut().rewind_to(checkpoint)
+    }
+
+    /// `shrink_to_fit` on a pinned tape.
+    #[track_caller]
+    pub fn shrink_to_fit_pinned(self: Pin<&mut Self>) -> usize {
+        self.pinned_mut().shrink_to_fit()
+    }
+
+    /// Whether a tape is active on the current thread.
+    pub fn has_active() -> bool {
+        ACTIVE.with(|active| !active.borrow().is_empty())
+    }
+
+    /// Run `f` with the tape active on the current thread, so that code deep
+    /// in a call stack can record without the tape being passed down to it.
+    ///
+    /// # Panics
+    /// If no tape is active.
+    pub fn with_active<R>(f: impl FnOnce(&Tape) -> R) -> R {
+        let tape = ACTIVE
+            .with(|active| active.borrow().last().copied())
+            .expect("no tape is active on this thread");
+        // Active tapes are pinned and removed from the stack when they are dropped.
+        f(unsafe { &*tape })
+    }
+}
+
+impl Drop for Tape {
+    fn drop(&mut self) {
+        // A leaked guard must not leave a dangling tape behind.
+        let this: *const Tape = self;
+        let _ = ACTIVE.try_with(|active| active.borrow_mut().retain(|tape| *tape != this));
+    }
+}
+
+/// # ActiveTape
+/// Keeps a tape active on the current thread; see `Tape::activate`.
+pub struct ActiveTape<'t> {
+    tape: &'t Tape,
+}
+
+impl Drop for ActiveTape<'_> {
+    fn drop(&mut self) {
+        let this: *const Tape = self.tape;
+        ACTIVE.with(|active| {
+            let mut active = active.borrow_mut();
+            if let Some(index) = active.iter().rposition(|tape| *tape == this) {
+                active.truncate(index);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adjoint(node: NonNull<ADNode>) -> f64 {
+        unsafe { node.as_ref().adjoint }
+    }
+
+    #[test]
+    fn test_tape_propagate_all() {
+        // f(x, y) = (x * y) + x at x = 2, y = 3
+        let tape = Tape::new();
+        let x = tape.record_leaf();
+        let y = tape.record_leaf();
+        let xy = unsafe { tape.record(&[(x, 3.0), (y, 2.0)]) };
+        let mut f = unsafe { tape.record(&[(xy, 1.0), (x, 1.0)]) };
+        assert_eq!(tape.len(), 4);
+        unsafe { f.as_mut().adjoint = 1.0 };
+        tape.propagate_all();
+        assert_eq!(adjoint(x), 4.0);
+        assert_eq!(adjoint(y), 2.0);
+    }
+
+    #[test]
+    fn test_tape_propagate_to_mark() {
+        let mut tape = Tape::new();
+        let x = tape.record_leaf();
+        let x2 = unsafe { tape.record(&[(x, 2.0)]) };
+        tape.mark();
+        for _ in 0..3 {
+            let mut y = unsafe { tape.record(&[(x2, 3.0)]) };
+            unsafe { y.as_mut().adjoint = 1.0 };
+            tape.propagate_to_mark();
+            tape.rewind_to_mark();
+        }
+        assert_eq!(tape.len(), 2);
+        assert_eq!(adjoint(x2), 9.0);
+        tape.propagate_mark_to_start();
+        assert_eq!(adjoint(x), 18.0);
+        tape.reset_adjoints();
+        assert_eq!(adjoint(x), 0.0);
+    }
+
+    #[test]
+    fn test_tape_many_nodes() {
+        let tape = Tape::new();
+        let x = tape.record_leaf();
+        let mut last = x;
+        for _ in 0..3 * NODE_BLOCK {
+            last = unsafe { tape.record(&[(last, 1.0)]) };
+        }
+        unsafe { last.as_mut().adjoint = 1.0 };
+        tape.propagate_all();
+        assert_eq!(adjoint(x), 1.0);
+        assert_eq!(tape.len(), 3 * NODE_BLOCK + 1);
+        assert_eq!(tape.capacity(), 4 * NODE_BLOCK);
+        assert!(tape.allocated_bytes() > tape.capacity() * std::mem::size_of::<ADNode>());
+    }
+
+    #[test]
+    fn test_tape_with_active() {
+        let outer = std::pin::pin!(Tape::new());
+        let inner = std::pin::pin!(Tape::new());
+        assert!(!Tape::has_active());
+        let guard = outer.as_ref().activate();
+        Tape::with_active(|tape| assert!(std::ptr::eq(tape, &*outer)));
+        {
+            let _guard = inner.as_ref().activate();
+            Tape::with_active(|tape| {
+                assert!(std::ptr::eq(tape, &*inner));
+                tape.record_leaf();
+            });
+        }
+        Tape::with_active(|tape| assert!(std::ptr::eq(tape, &*outer)));
+        drop(guard);
+        assert!(!Tape::has_active());
+        assert_eq!(inner.len(), 1);
+        assert!(outer.is_empty());
+    }
+
+    #[test]
+    fn test_tape_pinned_checkpoints() {
+        let mut tape = std::pin::pin!(Tape::new());
+        let x = {
+            let _guard = tape.as_ref().activate();
+            Tape::with_active(|tape| tape.record_leaf())
+        };
+        tape.as_mut().mark_pinned();
+        let start = tape.checkpoint();
+        for path in 1..=3 {
+            let guard = tape.as_ref().activate();
+            let mut y = Tape::with_active(|tape| unsafe { tape.record(&[(x, path as f64)]) });
+            unsafe { y.as_mut().adjoint = 1.0 };
+            tape.propagate_to_mark();
+            drop(guard);
+            assert_eq!(tape.len(), 2);
+            tape.as_mut().rewind_to_mark_pinned();
+        }
+        assert_eq!(adjoint(x), 6.0);
+        {
+            let _guard = tape.as_ref().activate();
+            Tape::with_active(|tape| tape.record_leaf());
+        }
+        tape.as_mut().rewind_to_pinned(start).unwrap();
+        assert_eq!(tape.len(), 1);
+        tape.as_mut().rewind_pinned();
+        assert!(tape.is_empty());
+        tape.as_mut().shrink_to_fit_pinned();
+    }
+
+    #[test]
+    fn test_tape_leaked_guard() {
+        {
+            let tape = std::pin::pin!(Tape::new());
+            std::mem::forget(tape.as_ref().activate());
+            assert!(Tape::has_active());
+        }
+        assert!(!Tape::has_active());
+    }
+
+    #[test]
+    #[should_panic(expected = "the tape is still active")]
+    fn test_tape_pinned_while_active() {
+        let mut tape = std::pin::pin!(Tape::new());
+        std::mem::forget(tape.as_ref().activate());
+        Tape::with_active(|active| {
+            let _ = active.record_leaf();
+            tape.as_mut().shrink_to_fit_pinned();
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tape_with_active_none() {
+        Tape::with_active(|_| ());
+    }
+
+    #[test]
+    fn test_tape_checkpoints() {
+        // Path-independent prefix: x2 = 2 * x, reused by every path.
+        let mut tape = Tape::new();
+        let start = tape.checkpoint();
+        let x = tape.record_leaf();
+        let x2 = unsafe { tape.record(&[(x, 2.0)]) };
+        let prefix = tape.checkpoint();
+        for path in 1..=3 {
+            let mut y = unsafe { tape.record(&[(x2, path as f64)]) };
+            let end = tape.checkpoint();
+            unsafe { y.as_mut().adjoint = 1.0 };
+            tape.propagate_between(prefix, end).unwrap();
+            tape.rewind_to(prefix).unwrap();
+            assert_eq!(tape.len(), 2);
+        }
+        assert_eq!(adjoint(x2), 6.0);
+        tape.propagate_between(start, prefix).unwrap();
+        assert_eq!(adjoint(x), 12.0);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the checkpoint was taken on another tape")]
+    fn test_tape_checkpoint_other_tape() {
+        let _policy = policy::default_policy();
+        let mut tape = Tape::new();
+        let other = Tape::new();
+        let _ = tape.rewind_to(other.checkpoint());
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the checkpoints are out of order")]
+    fn test_tape_checkpoints_out_of_order() {
+        let _policy = policy::default_policy();
+        let tape = Tape::new();
+        let start = tape.checkpoint();
+        tape.record_leaf();
+        let _ = tape.propagate_between(tape.checkpoint(), start);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the checkpoint is past the end of the tape")]
+    fn test_tape_checkpoint_past_end() {
+        let _policy = policy::default_policy();
+        let mut tape = Tape::new();
+        tape.record_leaf();
+        let end = tape.checkpoint();
+        tape.rewind();
+        let _ = tape.rewind_to(end);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "abort-on-violation", feature = "error-on-violation")))]
+    #[should_panic(expected = "the checkpoint is past the end of the tape")]
+    fn test_tape_checkpoint_after_shrink() {
+        let _policy = policy::default_policy();
+        let mut tape = Tape::new();
+        let mut last = tape.record_leaf();
+        for _ in 0..3 * NODE_BLOCK {
+            last = unsafe { tape.record(&[(last, 1.0)]) };
+        }
+        let end = tape.checkpoint();
+        tape.rewind();
+        assert!(tape.shrink_to_fit() > 0);
+        let mut last = tape.record_leaf();
+        for _ in 0..3 * NODE_BLOCK {
+            last = unsafe { tape.record(&[(last, 1.0)]) };
+        }
+        // The blocks `end` points into were freed and allocated again.
+        let _ = tape.rewind_to(end);
+    }
+
+    #[test]
+    fn test_tape_steady_state_is_allocation_free() {
+        let mut tape = Tape::new();
+        let x = tape.record_leaf();
+        tape.mark();
+        let mut blocks = 0;
+        for iteration in 0..3 {
+            let mut last = x;
+            for _ in 0..2 * NODE_BLOCK {
+                last = unsafe { tape.record(&[(last, 1.0), (x, 2.0), (x, 3.0)]) };
+            }
+            if iteration == 0 {
+                blocks = tape.block_count();
+            }
+            // Later iterations only reuse the blocks allocated by the first one.
+            assert_eq!(tape.block_count(), blocks);
+            tape.rewind_to_mark();
+        }
+    }
+
+    #[test]
+    fn test_tape_with_capacity() {
+        let tape = Tape::with_capacity(2 * NODE_BLOCK, DERIVATIVE_BLOCK + 1);
+        let blocks = tape.block_count();
+        assert_eq!(blocks, 2 + 2 * 2);
+        let mut last = tape.record_leaf();
+        for _ in 1..2 * NODE_BLOCK {
+            last = unsafe { tape.record(&[(last, 1.0)]) };
+        }
+        assert_eq!(tape.block_count(), blocks);
+    }
+}